@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use ndarray::{Array, IxDyn};
+use webcam_segmentation::{packed_to_planar, InputSpec, ResizeFilter, Resizer};
+
+const ITERS: u32 = 50;
+const SRC: (u32, u32) = (1280, 720);
+const DST: (usize, usize) = (640, 640);
+
+fn letterbox_size(w0: u32, h0: u32) -> (usize, usize) {
+    let r = (DST.0 as f32 / w0 as f32).min(DST.1 as f32 / h0 as f32);
+    (
+        (w0 as f32 * r).round() as usize,
+        (h0 as f32 * r).round() as usize,
+    )
+}
+
+fn legacy(img: &DynamicImage) -> Array<f32, IxDyn> {
+    // the original path: fresh tensor, CatmullRom resize, per-pixel indexing
+    let mut ys = Array::ones((1, 3, DST.1, DST.0)).into_dyn();
+    ys.fill(144.0 / 255.0);
+    let (w_new, h_new) = letterbox_size(img.width(), img.height());
+    let img = img.resize_exact(
+        w_new as u32,
+        h_new as u32,
+        image::imageops::FilterType::CatmullRom,
+    );
+    for (x, y, rgb) in img.pixels() {
+        let x = x as usize;
+        let y = y as usize;
+        let [r, g, b, _] = rgb.0;
+        ys[[0, 0, y, x]] = (r as f32) / 255.0;
+        ys[[0, 1, y, x]] = (g as f32) / 255.0;
+        ys[[0, 2, y, x]] = (b as f32) / 255.0;
+    }
+    ys
+}
+
+fn fast(img: &RgbaImage, resizer: &mut Resizer, spec: &InputSpec, ys: &mut [f32]) {
+    ys.fill(144.0 / 255.0);
+    let (w_new, h_new) = letterbox_size(img.width(), img.height());
+    let resized = resizer.resize(
+        img.as_raw(),
+        (img.width() as usize, img.height() as usize),
+        4,
+        (w_new, h_new),
+    );
+    packed_to_planar(resized, (w_new, h_new), 4, ys, DST, (0, 0), spec);
+}
+
+fn report(name: &str, total: Duration, baseline: Option<Duration>) {
+    let mean = total / ITERS;
+    match baseline {
+        Some(baseline) => println!(
+            "{:<20} {:>10.2?} ({:.1}x)",
+            name,
+            mean,
+            baseline.as_secs_f64() / total.as_secs_f64()
+        ),
+        None => println!("{:<20} {:>10.2?}", name, mean),
+    }
+}
+
+fn main() {
+    // synthetic gradient frame at webcam resolution
+    let rgba = RgbaImage::from_fn(SRC.0, SRC.1, |x, y| {
+        image::Rgba([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8, 255])
+    });
+    let img = DynamicImage::ImageRgba8(rgba.clone());
+    println!(
+        "Letterbox {}x{} -> {}x{}, {} iterations",
+        SRC.0, SRC.1, DST.0, DST.1, ITERS
+    );
+
+    let t = Instant::now();
+    for _ in 0..ITERS {
+        std::hint::black_box(legacy(&img));
+    }
+    let baseline = t.elapsed();
+    report("legacy (CatmullRom)", baseline, None);
+
+    let spec = InputSpec::default();
+    let mut ys = vec![0f32; 3 * DST.0 * DST.1];
+    for filter in [ResizeFilter::Bilinear, ResizeFilter::Area] {
+        let mut resizer = Resizer::new(filter);
+        fast(&rgba, &mut resizer, &spec, &mut ys); // build the weight tables once
+        let t = Instant::now();
+        for _ in 0..ITERS {
+            fast(&rgba, &mut resizer, &spec, &mut ys);
+            std::hint::black_box(&ys);
+        }
+        report(&format!("{:?}", filter), t.elapsed(), Some(baseline));
+    }
+}
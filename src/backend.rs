@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use ndarray::{Array, IxDyn};
+
+use crate::preprocess::parse_list;
+use crate::{parse_names, Args, DType, Error, Layout, Quantization, TensorView};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Backend {
+    // onnxruntime, or pure-Rust tract on CPU (needs the `tract` feature)
+    #[default]
+    Ort,
+    Tract,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OrtEP {
+    // ONNXRuntime execution provider
+    Cpu,
+    Cuda(u32),
+    Trt(u32),
+    OneDnn,
+    OpenVino,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OptLevel {
+    // ORT graph optimisation level
+    Disable,
+    Basic,
+    Extended,
+    #[default]
+    All,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LogLevel {
+    // ORT logging severity
+    Verbose,
+    Info,
+    #[default]
+    Warning,
+    Error,
+    Fatal,
+}
+
+#[derive(Debug, Clone)]
+pub struct Batch {
+    pub opt: u32,
+    pub min: u32,
+    pub max: u32,
+}
+
+impl Default for Batch {
+    fn default() -> Self {
+        Self {
+            opt: 1,
+            min: 1,
+            max: 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OrtConfig {
+    // ORT config
+    pub f: String,
+    pub ep: OrtEP,
+    pub trt_fp16: bool,
+    pub batch: Batch,
+    pub image_size: (Option<u32>, Option<u32>),
+    pub layout: Option<Layout>,
+    pub intra_threads: Option<i16>,
+    pub inter_threads: Option<i16>,
+    pub opt_level: OptLevel,
+    pub memory_arena: bool,
+    pub log_level: LogLevel,
+    pub profiling: Option<String>,
+}
+
+impl OrtConfig {
+    pub fn from_args(config: &Args) -> Self {
+        // execution provider
+        let ep = if config.trt {
+            OrtEP::Trt(config.device_id)
+        } else if config.cuda {
+            OrtEP::Cuda(config.device_id)
+        } else if config.openvino {
+            OrtEP::OpenVino
+        } else if config.onednn {
+            OrtEP::OneDnn
+        } else {
+            OrtEP::Cpu
+        };
+
+        // batch
+        let batch = Batch {
+            opt: config.batch,
+            min: config.batch_min,
+            max: config.batch_max,
+        };
+
+        Self {
+            ep,
+            batch,
+            f: config.model.clone(),
+            trt_fp16: config.fp16,
+            image_size: (config.height, config.width),
+            layout: config.layout,
+            intra_threads: config.intra_threads,
+            inter_threads: config.inter_threads,
+            opt_level: config.opt_level,
+            memory_arena: !config.no_memory_arena,
+            log_level: config.ort_log_level,
+            profiling: config.ort_profile.clone(),
+        }
+    }
+}
+
+pub fn build_backend(config: &Args) -> Result<Box<dyn InferenceBackend>, Error> {
+    // each backend is behind its own feature, `ort` is on by default
+    match config.backend {
+        #[cfg(feature = "ort")]
+        Backend::Ort => Ok(Box::new(crate::OrtBackend::build(OrtConfig::from_args(
+            config,
+        ))?)),
+        #[cfg(not(feature = "ort"))]
+        Backend::Ort => Err(Error::Config(
+            "`--backend ort` needs a build with the `ort` feature".to_string(),
+        )),
+        #[cfg(feature = "tract")]
+        Backend::Tract => Ok(Box::new(crate::TractBackend::build(
+            &OrtConfig::from_args(config),
+        )?)),
+        #[cfg(not(feature = "tract"))]
+        Backend::Tract => Err(Error::Config(
+            "`--backend tract` needs a build with `--features tract`".to_string(),
+        )),
+    }
+}
+
+pub fn output_quantization(
+    n: usize,
+    metadata: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<Quantization>, Error> {
+    // integer outputs: `output_scale` / `output_zero_point` metadata, one value or one per output
+    let per_output = |key| -> Result<Vec<Option<f32>>, Error> {
+        let xs = match metadata(key) {
+            Some(x) => parse_list(&x).ok_or_else(|| Error::Metadata {
+                key: key.to_string(),
+                reason: format!("expected a number or a list of numbers, got `{}`", x),
+            })?,
+            None => vec![],
+        };
+        Ok((0..n)
+            .map(|i| if xs.len() == 1 { xs.first() } else { xs.get(i) }.copied())
+            .collect())
+    };
+    Ok(per_output("output_scale")?
+        .into_iter()
+        .zip(per_output("output_zero_point")?)
+        .map(|(scale, zero_point)| Quantization {
+            scale: scale.unwrap_or(1.0),
+            zero_point: zero_point.unwrap_or(0.0) as i32,
+        })
+        .collect())
+}
+
+pub trait InferenceBackend: Send {
+    // what a model needs from its runtime: input geometry, metadata and one run at a time
+
+    fn dtype(&self) -> DType;
+
+    fn layout(&self) -> Layout;
+
+    fn batch(&self) -> u32;
+
+    fn height(&self) -> u32;
+
+    fn width(&self) -> u32;
+
+    fn is_batch_dynamic(&self) -> bool;
+
+    fn is_height_dynamic(&self) -> bool;
+
+    fn is_width_dynamic(&self) -> bool;
+
+    // -1 for dynamic axes
+    fn output_shapes(&self) -> Vec<Vec<i32>>;
+
+    fn fetch_from_metadata(&self, key: &str) -> Option<String>;
+
+    fn ep(&self) -> &OrtEP;
+
+    fn ep_confirmed(&self) -> bool {
+        // whether `ep` is known to be registered, or only what was asked for
+        true
+    }
+
+    // results land in `outputs`, which stay valid until the next run
+    fn run(&mut self, xs: TensorView, profile: bool) -> Result<()>;
+
+    fn outputs(&self) -> &[Array<f32, IxDyn>];
+
+    fn names(&self) -> Result<Option<BTreeMap<usize, String>>, Error> {
+        // class names, metadata parsing
+        // String format: `{0: 'person', 1: 'bicycle', 2: 'sports ball', ..., 27: "yellow_lady's_slipper"}`
+        let Some(names) = self.fetch_from_metadata("names") else {
+            return Ok(None);
+        };
+        parse_names(&names)
+            .map(Some)
+            .map_err(|reason| Error::Metadata {
+                key: "names".to_string(),
+                reason,
+            })
+    }
+
+    fn nm(&self) -> u32 {
+        // mask coefficients, 0 without a prototype output
+        self.output_shapes()
+            .get(1)
+            .and_then(|x| x.get(1))
+            .map_or(0, |&x| x.max(0) as u32)
+    }
+
+    fn author(&self) -> Option<String> {
+        self.fetch_from_metadata("author")
+    }
+
+    fn version(&self) -> Option<String> {
+        self.fetch_from_metadata("version")
+    }
+
+    fn end_profiling(&self) -> Result<Option<String>> {
+        // write out the runtime's profile if one is being recorded, returns its path
+        Ok(None)
+    }
+}
@@ -0,0 +1,166 @@
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use image::{Rgba, RgbaImage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum BenchFormat {
+    // aligned columns for reading, JSON for comparing runs across machines / models / EPs
+    #[default]
+    Table,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StageTimes {
+    // one `segment` call, split the way `--profile` prints it
+    pub preprocess: Duration,
+    pub inference: Duration,
+    pub postprocess: Duration,
+}
+
+impl AddAssign for StageTimes {
+    fn add_assign(&mut self, rhs: Self) {
+        self.preprocess += rhs.preprocess;
+        self.inference += rhs.inference;
+        self.postprocess += rhs.postprocess;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub n: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl Stats {
+    pub fn from_samples(xs: &[Duration]) -> Option<Self> {
+        // nearest-rank percentiles
+        if xs.is_empty() {
+            return None;
+        }
+        let mut xs = xs.to_vec();
+        xs.sort_unstable();
+        let n = xs.len();
+        let rank = |p: f64| xs[((p / 100.0 * n as f64).ceil() as usize).clamp(1, n) - 1];
+        Some(Self {
+            n,
+            min: xs[0],
+            mean: xs.iter().sum::<Duration>() / n as u32,
+            p50: rank(50.0),
+            p95: rank(95.0),
+            p99: rank(99.0),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Bench {
+    // samples per stage, stages are reported in the order they were first recorded
+    stages: Vec<(&'static str, Vec<Duration>)>,
+}
+
+impl Bench {
+    pub fn record(&mut self, stage: &'static str, t: Duration) {
+        match self.stages.iter_mut().find(|(name, _)| *name == stage) {
+            Some((_, xs)) => xs.push(t),
+            None => self.stages.push((stage, vec![t])),
+        }
+    }
+
+    pub fn time<T>(&mut self, stage: &'static str, f: impl FnOnce() -> T) -> T {
+        let t = Instant::now();
+        let y = f();
+        self.record(stage, t.elapsed());
+        y
+    }
+
+    pub fn record_stages(&mut self, times: StageTimes) {
+        self.record("preprocess", times.preprocess);
+        self.record("inference", times.inference);
+        self.record("postprocess", times.postprocess);
+    }
+
+    pub fn stats(&self) -> Vec<(&'static str, Stats)> {
+        self.stages
+            .iter()
+            .filter_map(|(name, xs)| Some((*name, Stats::from_samples(xs)?)))
+            .collect()
+    }
+
+    pub fn report(&self, format: BenchFormat) -> String {
+        match format {
+            BenchFormat::Table => self.table(),
+            BenchFormat::Json => self.json(),
+        }
+    }
+
+    pub fn table(&self) -> String {
+        let ms = |t: Duration| t.as_secs_f64() * 1e3;
+        let mut s = format!(
+            "{:<12} {:>7} {:>9} {:>9} {:>9} {:>9} {:>9}\n",
+            "stage (ms)", "n", "min", "mean", "p50", "p95", "p99"
+        );
+        for (name, x) in self.stats() {
+            s += &format!(
+                "{:<12} {:>7} {:>9.3} {:>9.3} {:>9.3} {:>9.3} {:>9.3}\n",
+                name,
+                x.n,
+                ms(x.min),
+                ms(x.mean),
+                ms(x.p50),
+                ms(x.p95),
+                ms(x.p99)
+            );
+        }
+        s
+    }
+
+    pub fn json(&self) -> String {
+        // stage names are plain identifiers, nothing to escape
+        let ms = |t: Duration| t.as_secs_f64() * 1e3;
+        let stages: Vec<String> = self
+            .stats()
+            .into_iter()
+            .map(|(name, x)| {
+                format!(
+                    "{{\"stage\":\"{}\",\"n\":{},\"min_ms\":{:.4},\"mean_ms\":{:.4},\
+                    \"p50_ms\":{:.4},\"p95_ms\":{:.4},\"p99_ms\":{:.4}}}",
+                    name,
+                    x.n,
+                    ms(x.min),
+                    ms(x.mean),
+                    ms(x.p50),
+                    ms(x.p95),
+                    ms(x.p99)
+                )
+            })
+            .collect();
+        format!("{{\"stages\":[{}]}}", stages.join(","))
+    }
+}
+
+pub fn synthetic_frame(width: u32, height: u32) -> RgbaImage {
+    // gradient with some noise, a flat frame compresses and decodes unrealistically fast
+    let mut seed = 0x2545_f491_u32;
+    RgbaImage::from_fn(width, height, |x, y| {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        let noise = seed & 0x1f;
+        let r = x * 255 / width.max(1);
+        let g = y * 255 / height.max(1);
+        let b = (x + y) * 127 / (width + height).max(1);
+        Rgba([
+            (r + noise).min(255) as u8,
+            (g + noise).min(255) as u8,
+            (b + noise).min(255) as u8,
+            255,
+        ])
+    })
+}
@@ -1,14 +1,51 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+
+use crate::{
+    Backend, BenchFormat, ChannelOrder, Layout, LogLevel, OptLevel, OutputLayout, PixelRange,
+    Propagation, ResizeFilter, SegmenterKind, SoftNms,
+};
+
+#[derive(Parser, Clone)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
+pub struct Cli {
+    // the camera pipeline, or one of the subcommands, which need no `--model` / `--source`
+    #[command(subcommand)]
+    pub command: Option<SubCommand>,
+
+    #[command(flatten)]
+    pub args: Args,
+}
 
 #[derive(Parser, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
     /// ONNX model path
-    #[arg(long, required = true)]
+    #[arg(
+        long,
+        required_unless_present = "model_name",
+        default_value = "",
+        hide_default_value = true
+    )]
     pub model: String,
 
+    /// model from the registry in `--models-dir`, its manifest fills in flags not given here
+    #[arg(long)]
+    pub model_name: Option<String>,
+
+    /// directory of `<name>.toml` model manifests
+    #[arg(long, global = true, default_value = "models")]
+    pub models_dir: String,
+
+    /// model family, yolo instance segmentation, portrait or video matting
+    #[arg(long, value_enum, default_value_t = SegmenterKind::Yolo)]
+    pub segmenter: SegmenterKind,
+
+    /// inference backend, `tract` is pure Rust and CPU only
+    #[arg(long, value_enum, default_value_t = Backend::Ort)]
+    pub backend: Backend,
+
     /// input path
-    #[arg(long, required = true)]
+    #[arg(long, required = true, default_value = "", hide_default_value = true)]
     pub source: String,
 
     /// device id
@@ -23,6 +60,47 @@ pub struct Args {
     #[arg(long)]
     pub cuda: bool,
 
+    /// using OpenVINO EP on the CPU
+    #[arg(long)]
+    pub openvino: bool,
+
+    /// using oneDNN (DNNL) EP, XNNPACK is not supported by the onnxruntime bindings
+    #[arg(long)]
+    pub onednn: bool,
+
+    /// ORT intra-op thread count [default: ORT picks]
+    #[arg(long)]
+    pub intra_threads: Option<i16>,
+
+    /// ORT inter-op thread count, enables parallel execution [default: ORT picks]
+    #[arg(long)]
+    pub inter_threads: Option<i16>,
+
+    /// ORT graph optimisation level
+    #[arg(long, value_enum, default_value_t = OptLevel::All)]
+    pub opt_level: OptLevel,
+
+    /// disable the ORT CPU memory arena and memory pattern
+    #[arg(long)]
+    pub no_memory_arena: bool,
+
+    /// ORT log level
+    #[arg(long, value_enum, default_value_t = LogLevel::Warning)]
+    pub ort_log_level: LogLevel,
+
+    /// write ORT's JSON profile to this path
+    #[arg(long)]
+    pub ort_profile: Option<String>,
+
+    /// class names file, a `{0: 'person', ...}` / JSON literal or one name per line,
+    /// overrides the model's `names` metadata
+    #[arg(long)]
+    pub names: Option<String>,
+
+    /// only keep these classes, by name, e.g. `--classes person,dog` [default: all]
+    #[arg(long, value_delimiter = ',')]
+    pub classes: Vec<String>,
+
     /// input batch size
     #[arg(long, default_value_t = 1)]
     pub batch: u32,
@@ -51,6 +129,10 @@ pub struct Args {
     #[arg(long)]
     pub nm: Option<u32>,
 
+    /// detection head layout, detected from output shapes and metadata when unset
+    #[arg(long, value_enum)]
+    pub output_layout: Option<OutputLayout>,
+
     /// input image width
     #[arg(long)]
     pub width: Option<u32>,
@@ -59,6 +141,46 @@ pub struct Args {
     #[arg(long)]
     pub height: Option<u32>,
 
+    /// resize filter used for letterboxing
+    #[arg(long, value_enum, default_value_t = ResizeFilter::Bilinear)]
+    pub resize: ResizeFilter,
+
+    /// centre the letterbox like Ultralytics' training pipeline
+    #[arg(long)]
+    pub center: bool,
+
+    /// letterbox pad colour as r,g,b
+    #[arg(long, value_delimiter = ',', default_values_t = [144, 144, 144])]
+    pub pad_color: Vec<u8>,
+
+    /// model channel order, overrides `channel_order` metadata [default: rgb]
+    #[arg(long, value_enum)]
+    pub channel_order: Option<ChannelOrder>,
+
+    /// pixel range before mean/std, overrides `pixel_range` metadata [default: 0-1]
+    #[arg(long, value_enum)]
+    pub pixel_range: Option<PixelRange>,
+
+    /// per-channel mean as a,b,c, overrides `mean` metadata [default: 0,0,0]
+    #[arg(long, value_delimiter = ',')]
+    pub mean: Option<Vec<f32>>,
+
+    /// per-channel std as a,b,c, overrides `std` metadata [default: 1,1,1]
+    #[arg(long, value_delimiter = ',')]
+    pub std: Option<Vec<f32>>,
+
+    /// integer input quantisation scale, overrides `input_scale` metadata
+    #[arg(long)]
+    pub input_scale: Option<f32>,
+
+    /// integer input quantisation zero point, overrides `input_zero_point` metadata
+    #[arg(long, allow_hyphen_values = true)]
+    pub input_zero_point: Option<i32>,
+
+    /// input tensor layout, overrides `layout` metadata and shape detection [default: nchw]
+    #[arg(long, value_enum)]
+    pub layout: Option<Layout>,
+
     /// confidence threshold
     #[arg(long, required = false, default_value_t = 0.3)]
     pub conf: f32,
@@ -67,6 +189,58 @@ pub struct Args {
     #[arg(long, required = false, default_value_t = 0.45)]
     pub iou: f32,
 
+    /// only suppress boxes of the same class in NMS
+    #[arg(long)]
+    pub class_aware: bool,
+
+    /// maximum detections kept after NMS
+    #[arg(long, default_value_t = 300)]
+    pub max_det: usize,
+
+    /// only the top-k candidates by confidence enter NMS
+    #[arg(long)]
+    pub nms_top_k: Option<usize>,
+
+    /// decay overlapping scores instead of dropping boxes
+    #[arg(long, value_enum)]
+    pub soft_nms: Option<SoftNms>,
+
+    /// sigma of gaussian soft-NMS
+    #[arg(long, default_value_t = 0.5)]
+    pub soft_nms_sigma: f32,
+
+    /// binarise masks at this probability, soft mattes are kept when unset
+    #[arg(long)]
+    pub mask_threshold: Option<f32>,
+
+    /// pixels kept around each bbox when cropping its mask
+    #[arg(long, default_value_t = 10.0)]
+    pub mask_padding: f32,
+
+    /// run on a crop around the previous person at full frame resolution
+    #[arg(long)]
+    pub roi: bool,
+
+    /// roi padding on each side, as a fraction of the previous bbox size
+    #[arg(long, default_value_t = 0.25)]
+    pub roi_padding: f32,
+
+    /// run the model every N frames, 0 runs it whenever the inference thread is free
+    #[arg(long, default_value_t = 1)]
+    pub infer_every: u32,
+
+    /// how the last mask follows the person on frames without inference
+    #[arg(long, value_enum, default_value_t = Propagation::Flow)]
+    pub propagate: Propagation,
+
+    /// rvm internal downsample ratio, defaults to ~512px on the long side
+    #[arg(long)]
+    pub downsample_ratio: Option<f32>,
+
+    /// rvm scene cut threshold, mean luma difference between frames that resets the state
+    #[arg(long, default_value_t = 0.2)]
+    pub scene_cut: f32,
+
     /// confidence threshold of keypoint
     #[arg(long, required = false, default_value_t = 0.55)]
     pub kconf: f32,
@@ -74,4 +248,49 @@ pub struct Args {
     /// check time consumed in each stage
     #[arg(long)]
     pub profile: bool,
+
+    /// warm-up runs on a blank frame before the stream starts, 0 skips them and the output check
+    #[arg(long, default_value_t = 3)]
+    pub warmup: usize,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum SubCommand {
+    /// list the models in `--models-dir`
+    ListModels,
+
+    /// time decode, preprocess, inference, postprocess, composite and encode per frame
+    Bench(BenchArgs),
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct BenchArgs {
+    /// frames to run
+    #[arg(long, default_value_t = 100)]
+    pub frames: usize,
+
+    /// image to use for every frame, a synthetic one otherwise
+    #[arg(long)]
+    pub input: Option<String>,
+
+    /// synthetic frame width
+    #[arg(long, default_value_t = 1280)]
+    pub frame_width: u32,
+
+    /// synthetic frame height
+    #[arg(long, default_value_t = 720)]
+    pub frame_height: u32,
+
+    /// report format
+    #[arg(long, value_enum, default_value_t = BenchFormat::Table)]
+    pub format: BenchFormat,
+}
+
+impl Args {
+    pub fn pad_rgb(&self) -> anyhow::Result<[u8; 3]> {
+        match self.pad_color[..] {
+            [r, g, b] => Ok([r, g, b]),
+            _ => anyhow::bail!("`--pad-color` expects r,g,b, got {:?}", self.pad_color),
+        }
+    }
 }
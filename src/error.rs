@@ -0,0 +1,26 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    // what can go wrong while setting up a model, so embedders can report it instead of aborting
+    #[error("Failed to load model `{path}`: {reason}")]
+    Load { path: String, reason: String },
+
+    #[error("{0}")]
+    Shape(String),
+
+    #[error("{0}")]
+    Dtype(String),
+
+    #[error("Bad metadata `{key}`: {reason}")]
+    Metadata { key: String, reason: String },
+
+    #[error("Execution provider: {0}")]
+    Ep(String),
+
+    #[error("{0}")]
+    Config(String),
+
+    #[error("Warm-up run failed: {0}")]
+    Warmup(String),
+}
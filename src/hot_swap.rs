@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use clap::{ArgAction, CommandFactory, FromArgMatches};
+
+use crate::{build_segmenter, Args, Segmenter};
+
+pub enum Command {
+    // `load <model> [flags..]`: build a new segmenter from the running flags plus these
+    Load(Box<Args>),
+    // `quit`: stop between frames, after writing out `--ort-profile`
+    Quit,
+}
+
+impl Command {
+    pub fn parse(line: &str, base: &Args) -> Result<Self> {
+        // whitespace separated, no quoting
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("load") => {
+                let model = words
+                    .next()
+                    .ok_or_else(|| anyhow!("usage: load <model> [flags..]"))?;
+                let matches = overrides()
+                    .try_get_matches_from(["load", "--model", model].into_iter().chain(words))?;
+                let mut args = base.clone();
+                args.update_from_arg_matches(&matches)?;
+                Ok(Command::Load(Box::new(args)))
+            }
+            Some("quit" | "exit") => Ok(Command::Quit),
+            Some(cmd) => bail!(
+                "unknown command `{}`, expected `load <model> [flags..]` or `quit`",
+                cmd
+            ),
+            None => bail!("empty command"),
+        }
+    }
+}
+
+fn overrides() -> clap::Command {
+    // `Args` without defaults or required flags, so only what's written on the line
+    // ends up in the matches and everything else keeps the running value
+    Args::command().mut_args(|a| {
+        let a = a.required(false).default_value(None);
+        match a.get_action() {
+            // flags get an implicit `false` default otherwise
+            ArgAction::SetTrue => a
+                .action(ArgAction::Set)
+                .num_args(0)
+                .default_missing_value("true"),
+            _ => a,
+        }
+    })
+}
+
+pub fn hot_swap() -> (ModelLoader, ModelSwap) {
+    // loader side takes commands, swap side lives with the running model
+    let (tx, rx) = channel();
+    let quit = Arc::new(AtomicBool::new(false));
+    (
+        ModelLoader {
+            tx,
+            quit: quit.clone(),
+        },
+        ModelSwap { rx, quit },
+    )
+}
+
+#[derive(Clone)]
+pub struct ModelLoader {
+    tx: Sender<(String, Result<Box<dyn Segmenter>>)>,
+    quit: Arc<AtomicBool>,
+}
+
+impl ModelLoader {
+    pub fn load(&self, args: Args) {
+        // build on a background thread, the running model keeps going meanwhile
+        let name = args.model.clone();
+        self.load_with(name, move || build_segmenter(args));
+    }
+
+    pub fn load_with(
+        &self,
+        name: String,
+        f: impl FnOnce() -> Result<Box<dyn Segmenter>> + Send + 'static,
+    ) {
+        let tx = self.tx.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send((name, f()));
+        });
+    }
+
+    pub fn quit(&self) {
+        // picked up by `ModelSwap::quitting` on the thread running the model
+        self.quit.store(true, Ordering::Relaxed);
+    }
+}
+
+pub struct ModelSwap {
+    rx: Receiver<(String, Result<Box<dyn Segmenter>>)>,
+    quit: Arc<AtomicBool>,
+}
+
+impl ModelSwap {
+    pub fn poll(&self, model: &mut Box<dyn Segmenter>) -> Option<Result<String>> {
+        // call between frames: swaps in a finished load, a failed one leaves `model` as is.
+        // returns the model name that was swapped in, or why it wasn't
+        let (name, loaded) = self.rx.try_recv().ok()?;
+        Some(match loaded {
+            Ok(new) => {
+                *model = new;
+                Ok(name)
+            }
+            Err(e) => Err(e.context(format!(
+                "Failed to load `{}`, keeping the current model",
+                name
+            ))),
+        })
+    }
+
+    pub fn quitting(&self) -> bool {
+        self.quit.load(Ordering::Relaxed)
+    }
+}
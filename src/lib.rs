@@ -1,37 +1,65 @@
 #![allow(clippy::type_complexity)]
 
+pub mod backend;
+pub mod bench;
 pub mod cli;
+pub mod error;
+pub mod hot_swap;
+#[cfg(feature = "ort")]
+pub mod matting;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod model;
+pub mod names;
+pub mod nms;
+pub mod onnx;
+#[cfg(feature = "ort")]
 pub mod ort_backend;
+pub mod output_layout;
+pub mod preprocess;
+pub mod propagate;
+pub mod registry;
+pub mod roi;
+#[cfg(feature = "ort")]
+pub mod rvm;
+pub mod segmenter;
+pub mod tensor;
+#[cfg(feature = "tract")]
+pub mod tract_backend;
 pub mod yolo_result;
-pub use crate::cli::Args;
+pub use crate::backend::{
+    build_backend, output_quantization, Backend, Batch, InferenceBackend, LogLevel, OptLevel,
+    OrtConfig, OrtEP,
+};
+pub use crate::bench::{synthetic_frame, Bench, BenchFormat, StageTimes, Stats};
+pub use crate::cli::{Args, BenchArgs, Cli, SubCommand};
+pub use crate::error::Error;
+pub use crate::hot_swap::{hot_swap, Command, ModelLoader, ModelSwap};
+#[cfg(feature = "ort")]
+pub use crate::matting::PortraitMatting;
+#[cfg(any(test, feature = "mock"))]
+pub use crate::mock::MockBackend;
 pub use crate::model::YOLOv8;
-pub use crate::ort_backend::{Batch, OrtBackend, OrtConfig, OrtEP};
-pub use crate::yolo_result::{Bbox, Embedding, Point2, YOLOResult};
-
-pub fn non_max_suppression(
-    xs: &mut Vec<(Bbox, Option<Vec<Point2>>, Option<Vec<f32>>)>,
-    iou_threshold: f32,
-) {
-    xs.sort_by(|b1, b2| b2.0.confidence.partial_cmp(&b1.0.confidence).unwrap());
-
-    let mut current_index = 0;
-    for index in 0..xs.len() {
-        let mut drop = false;
-        for prev_index in 0..current_index {
-            let iou = xs[prev_index].0.iou(&xs[index].0);
-            if iou > iou_threshold {
-                drop = true;
-                break;
-            }
-        }
-        if !drop {
-            xs.swap(current_index, index);
-            current_index += 1;
-        }
-    }
-    xs.truncate(current_index);
-}
+pub use crate::names::{parse_names, read_names};
+pub use crate::nms::{non_max_suppression, non_max_suppression_with, NmsConfig, SoftNms};
+pub use crate::onnx::{ModelInfo, ModelInputs};
+#[cfg(feature = "ort")]
+pub use crate::ort_backend::{OrtBackend, Recurrent};
+pub use crate::output_layout::OutputLayout;
+pub use crate::preprocess::{
+    fill_pad, letterbox_into, packed_to_interleaved, packed_to_planar, unletterbox, ChannelOrder,
+    InputElement, InputSpec, Layout, Letterbox, PixelRange, Quantization, ResizeFilter, Resizer,
+};
+pub use crate::propagate::{translate, BboxMotion, Propagation};
+pub use crate::registry::{ManifestDtype, ModelManifest, ModelRegistry};
+pub use crate::roi::RoiSegmenter;
+#[cfg(feature = "ort")]
+pub use crate::rvm::{SceneCut, VideoMatting};
+pub use crate::segmenter::{build_segmenter, Matte, Segmenter, SegmenterKind};
+pub use crate::tensor::{DType, InputTensor, TensorView};
+#[cfg(feature = "tract")]
+pub use crate::tract_backend::TractBackend;
+pub use crate::yolo_result::{Bbox, Embedding, Mask, Point2, YOLOResult};
 
 pub fn gen_time_string(delimiter: &str) -> String {
     let offset = chrono::FixedOffset::east_opt(8 * 60 * 60).unwrap(); // Beijing
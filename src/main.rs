@@ -1,16 +1,19 @@
 use std::time::Instant;
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 
-use crossbeam_channel::Receiver;
-use opencv::core::{Size, CV_8UC4};
+use crossbeam_channel::{Receiver, Sender};
+use opencv::core::{Size, Vec2f, CV_32FC2, CV_8UC4};
 use opencv::prelude::*;
 
 use image::DynamicImage;
 use turbojpeg::Compressor;
 use turbojpeg::Decompressor;
 use turbojpeg::OutputBuf;
-use webcam_segmentation::{Args, YOLOv8};
+use webcam_segmentation::{
+    build_segmenter, hot_swap, synthetic_frame, translate, Args, BboxMotion, Bench, BenchArgs, Cli,
+    Command, Mask, Matte, ModelRegistry, ModelSwap, Propagation, Segmenter, SubCommand,
+};
 
 use v4l::buffer::{Metadata, Type};
 use v4l::io::mmap::Stream;
@@ -21,11 +24,50 @@ use v4l::FourCC;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let _ = opencv::core::set_num_threads(1);
-    let mut args = Args::parse();
+    let matches = Cli::command().get_matches();
+    let Cli { command, mut args } = Cli::from_arg_matches(&matches)?;
     //args.profile = true;
 
-    let model = YOLOv8::new(args).unwrap();
+    if let Some(SubCommand::ListModels) = command {
+        ModelRegistry::open(&args.models_dir)?.list();
+        return Ok(());
+    }
+    // `--model-name` fills in the flags not given from `<models-dir>/<name>.toml`
+    ModelRegistry::resolve(&mut args, &matches)?;
+    if let Some(SubCommand::Bench(opts)) = command {
+        return bench(args, opts);
+    }
+
+    let (infer_every, propagate) = (args.infer_every, args.propagate);
+    let base = args.clone();
+    let model = build_segmenter(args).unwrap();
     model.summary(); // model info
+    let (loader, swap) = hot_swap();
+    let inference = Inference::new(model, infer_every, swap);
+
+    // ========== Model Commands ==========
+
+    // e.g. `load yolov8s-seg.onnx --conf 0.4` on stdin, the model is built in the background
+    // and swapped in between frames, the camera pipeline keeps running. `quit` stops it
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match Command::parse(&line, &base) {
+                Ok(Command::Load(args)) => {
+                    println!("> Loading {} ...", args.model);
+                    loader.load(*args);
+                }
+                Ok(Command::Quit) => {
+                    println!("> Quitting after this frame ...");
+                    loader.quit();
+                }
+                Err(e) => println!("> {:#}", e),
+            }
+        }
+    });
 
     // ========== Create Input Device ==========
 
@@ -96,7 +138,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .expect("Failed to create buffer stream");
 
     let (tx, rx) = crossbeam_channel::bounded(4);
-    let _process_task = process(rx, model, webcam_masked, width, height);
+    let _process_task = process(rx, inference, propagate, webcam_masked, width, height);
 
     loop {
         let (jpeg, buf_in_meta) = CaptureStream::next(&mut in_stream).unwrap();
@@ -107,20 +149,283 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 }
 
+enum Inference {
+    // every N frames on the processing thread
+    Inline {
+        model: Box<dyn Segmenter>,
+        every: u32,
+        swap: ModelSwap,
+    },
+    // whenever the inference thread is free, results arrive a few frames late
+    Worker {
+        tx: Sender<(u64, DynamicImage)>,
+        rx: Receiver<(u64, Option<Matte>)>,
+        in_flight: Option<u64>,
+    },
+}
+
+impl Inference {
+    fn new(mut model: Box<dyn Segmenter>, every: u32, swap: ModelSwap) -> Self {
+        if every > 0 {
+            return Inference::Inline { model, every, swap };
+        }
+        let (tx, frames) = crossbeam_channel::bounded::<(u64, DynamicImage)>(1);
+        let (mattes, rx) = crossbeam_channel::bounded(1);
+        std::thread::spawn(move || {
+            for (idx, img) in frames {
+                poll_swap(&swap, &mut model);
+                let matte = model.segment(&img).unwrap();
+                if mattes.send((idx, matte)).is_err() {
+                    break;
+                }
+            }
+        });
+        Inference::Worker {
+            tx,
+            rx,
+            in_flight: None,
+        }
+    }
+
+    fn step(&mut self, idx: u64, img: &DynamicImage) -> Option<(u64, Option<Matte>)> {
+        // a fresh result (tagged with the frame it was computed on), `None` when the last
+        // mask has to be propagated to this frame
+        match self {
+            Inference::Inline { model, every, swap } => {
+                poll_swap(swap, model);
+                (idx % *every as u64 == 0).then(|| (idx, model.segment(img).unwrap()))
+            }
+            Inference::Worker { tx, rx, in_flight } => {
+                let fresh = rx.try_recv().ok();
+                if fresh.is_some() {
+                    *in_flight = None;
+                }
+                if in_flight.is_none() {
+                    tx.send((idx, img.clone())).unwrap();
+                    *in_flight = Some(idx);
+                }
+                fresh
+            }
+        }
+    }
+
+    fn in_flight(&self) -> Option<u64> {
+        // frame the worker is segmenting, its result lands on a later frame
+        match self {
+            Inference::Inline { .. } => None,
+            Inference::Worker { in_flight, .. } => *in_flight,
+        }
+    }
+
+    fn propagates(&self) -> bool {
+        // inference on every frame leaves nothing to propagate
+        !matches!(self, Inference::Inline { every: 1, .. })
+    }
+}
+
+fn write_alpha(mask: &Mask, greyscale: &mut Mat) {
+    // probabilities -> 8-bit alpha for opencv
+    for (dst, &p) in greyscale
+        .data_bytes_mut()
+        .unwrap()
+        .iter_mut()
+        .zip(mask.as_raw())
+    {
+        *dst = (p * 255.0).round() as u8;
+    }
+}
+
+fn poll_swap(swap: &ModelSwap, model: &mut Box<dyn Segmenter>) {
+    // between frames, so a frame never sees two models
+    match swap.poll(model) {
+        Some(Ok(name)) => {
+            println!("> Swapped in {}", name);
+            model.summary();
+        }
+        Some(Err(e)) => println!("> {:#}", e),
+        None => {}
+    }
+    if swap.quitting() {
+        // the camera loop never returns, this is the only way out that writes the profile
+        end_profiling(model.as_ref());
+        std::process::exit(0);
+    }
+}
+
+fn end_profiling(model: &dyn Segmenter) {
+    match model.end_profiling() {
+        Ok(Some(path)) => println!("> ORT profile: {}", path),
+        Ok(None) => {}
+        Err(e) => println!("> Failed to write the ORT profile: {:#}", e),
+    }
+}
+
+struct FlowWarp {
+    // dense optical flow on downscaled grey frames, drags the last mask along
+    size: Size,
+    small: Size,
+    grey: Mat,
+    prev: Mat,
+    curr: Mat,
+    flow_small: Mat,
+    flow: Mat,
+    grid: Mat,
+    // backward map, current frame -> previous frame, computed at most once per frame
+    map: Mat,
+    mapped: bool,
+    // backward maps, current frame -> the frame in flight on the worker / the finished one
+    acc: Mat,
+    done: Mat,
+    scratch: Mat,
+    warped: Mat,
+}
+
+impl FlowWarp {
+    const SCALE: f64 = 0.25;
+
+    fn new(width: usize, height: usize) -> opencv::Result<Self> {
+        let size = Size::new(width as i32, height as i32);
+        let small = Size::new(
+            (width as f64 * Self::SCALE).round() as i32,
+            (height as f64 * Self::SCALE).round() as i32,
+        );
+
+        // identity map, pixel (x, y) samples (x, y)
+        let mut grid = Mat::new_size_with_default(size, CV_32FC2, opencv::core::Scalar::all(0.0))?;
+        for (i, p) in grid.data_typed_mut::<Vec2f>()?.iter_mut().enumerate() {
+            *p = Vec2f::from([(i % width) as f32, (i / width) as f32]);
+        }
+
+        Ok(Self {
+            size,
+            small,
+            grey: Mat::default(),
+            prev: Mat::default(),
+            curr: Mat::default(),
+            flow_small: Mat::default(),
+            flow: Mat::default(),
+            grid,
+            map: Mat::default(),
+            mapped: false,
+            acc: Mat::default(),
+            done: Mat::default(),
+            scratch: Mat::default(),
+            warped: Mat::default(),
+        })
+    }
+
+    fn update(&mut self, rgba: &Mat) -> opencv::Result<()> {
+        // push the current frame, called on every frame
+        use opencv::imgproc::*;
+        std::mem::swap(&mut self.prev, &mut self.curr);
+        self.mapped = false;
+        cvt_color(rgba, &mut self.grey, COLOR_RGBA2GRAY, 0)?;
+        resize(&self.grey, &mut self.curr, self.small, 0.0, 0.0, INTER_AREA)
+    }
+
+    fn step(&mut self) -> opencv::Result<bool> {
+        // flow between the last two frames as a sampling map, false before there are two
+        use opencv::{core::*, imgproc::*, video::*};
+        if self.prev.empty() {
+            return Ok(false);
+        }
+        if self.mapped {
+            return Ok(true);
+        }
+
+        // backward flow: where each current pixel was in the previous frame
+        calc_optical_flow_farneback(
+            &self.curr,
+            &self.prev,
+            &mut self.flow_small,
+            0.5,
+            3,
+            15,
+            3,
+            5,
+            1.2,
+            0,
+        )?;
+        resize(
+            &self.flow_small,
+            &mut self.flow,
+            self.size,
+            0.0,
+            0.0,
+            INTER_LINEAR,
+        )?;
+        scale_add(&self.flow, 1.0 / Self::SCALE, &self.grid, &mut self.map)?;
+        self.mapped = true;
+        Ok(true)
+    }
+
+    fn warp(&mut self, mask: &mut Mat) -> opencv::Result<()> {
+        // previous frame's mask -> current frame
+        if self.step()? {
+            Self::remap(mask, &self.map, &mut self.warped)?;
+            self.warped.copy_to(mask)?;
+        }
+        Ok(())
+    }
+
+    fn track(&mut self) -> opencv::Result<()> {
+        // a frame went to the worker: the map so far belongs to the result that just came
+        // back, start a new one from this frame
+        std::mem::swap(&mut self.acc, &mut self.done);
+        self.grid.copy_to(&mut self.acc)
+    }
+
+    fn accumulate(&mut self) -> opencv::Result<()> {
+        // extend the in-flight map by one frame, acc(x) <- acc(map(x))
+        use opencv::{core::*, imgproc::*};
+        if self.acc.empty() || !self.step()? {
+            return Ok(());
+        }
+        remap(
+            &self.acc,
+            &mut self.scratch,
+            &self.map,
+            &no_array(),
+            INTER_LINEAR,
+            BORDER_REPLICATE,
+            Scalar::all(0.0),
+        )?;
+        std::mem::swap(&mut self.acc, &mut self.scratch);
+        Ok(())
+    }
+
+    fn warp_tracked(&mut self, mask: &mut Mat) -> opencv::Result<()> {
+        // worker result -> current frame, across every frame since it was submitted
+        if !self.done.empty() {
+            Self::remap(mask, &self.done, &mut self.warped)?;
+            self.warped.copy_to(mask)?;
+        }
+        Ok(())
+    }
+
+    fn remap(mask: &Mat, map: &Mat, dst: &mut Mat) -> opencv::Result<()> {
+        use opencv::{core::*, imgproc::*};
+        remap(
+            mask,
+            dst,
+            map,
+            &no_array(),
+            INTER_LINEAR,
+            BORDER_CONSTANT,
+            Scalar::all(0.0),
+        )
+    }
+}
+
 fn process(
     rx: Receiver<(Vec<u8>, Metadata)>,
-    mut model: YOLOv8,
+    mut inference: Inference,
+    propagate: Propagation,
     webcam_masked: Device,
     width: usize,
     height: usize,
 ) -> std::thread::JoinHandle<()> {
     std::thread::spawn(move || {
-        let person_index = model
-            .names()
-            .iter()
-            .enumerate()
-            .find_map(|(idx, name)| if name == "person" { Some(idx) } else { None })
-            .expect("Model missing `person` bbox name");
         // ========== General Allocations ==========
         let mut out_stream = Stream::with_buffers(&webcam_masked, Type::VideoOutput, 4).unwrap();
 
@@ -128,6 +433,15 @@ fn process(
         let mut decompressor = Decompressor::new().unwrap();
         let mut jpeg_buf = OutputBuf::new_owned();
         let mut rgba_pixels = vec![0; 4 * (width * height) as usize];
+        let mut mask_pixels = vec![0u8; width * height];
+
+        // mask propagation between inferences
+        let mut flow = FlowWarp::new(width, height).unwrap();
+        let mut motion = BboxMotion::default();
+        let mut tracking = false;
+        let mut keyframe: Option<Mask> = None;
+        let mut shifted = Mask::new(width as u32, height as u32);
+        let use_flow = propagate == Propagation::Flow && inference.propagates();
 
         // SAFETY: Memory allocated by opencv
         let mut mask_rgba = unsafe {
@@ -154,7 +468,7 @@ fn process(
         .unwrap();
 
         // ========== Process Frame Loop ==========
-        loop {
+        for idx in 0u64.. {
             let (jpeg, buf_in_meta) = rx.recv().unwrap();
 
             let (buf_out, buf_out_meta) =
@@ -208,30 +522,24 @@ fn process(
             let rgba8 = image::RgbaImage::from_raw(width as u32, height as u32, rgba_data).unwrap();
 
             let img = DynamicImage::ImageRgba8(rgba8);
+            if use_flow {
+                flow.update(&rgba).unwrap();
+                if inference.in_flight().is_some() {
+                    flow.accumulate().unwrap();
+                }
+            }
+
             let start = Instant::now();
-            let ys = model.run(&img).unwrap();
+            let ys = inference.step(idx, &img);
             //println!("Model eval took: {:?}", start.elapsed());
+            if use_flow && inference.in_flight() == Some(idx) {
+                flow.track().unwrap();
+            }
 
-            let Some(mut ys) = ys else {
-                continue;
-            };
-            let Some(person_index) = ys.bboxes.iter().position(|bb| bb.id == person_index) else {
-                //println!("No person found");
-                continue;
-            };
-
-            let Some(mask) = ys.masks.get_mut(person_index) else {
-                continue;
-            };
-
-            let mut mask_pixels = mask.as_flat_samples_mut();
-            let mask_pixels = mask_pixels.as_mut_slice();
-
-            assert_eq!(width * height, mask_pixels.len().try_into().unwrap());
             // SAFETY:
-            // By assert above, pixels is readable for `width * height` bytes, since it has 8
-            // bits per channel
-            let greyscale = unsafe {
+            // `mask_pixels` lives for the entire duration of the loop and has len
+            // `width * height` by allocation above
+            let mut greyscale = unsafe {
                 Mat::new_size_with_data(
                     Size {
                         width: width as i32,
@@ -244,6 +552,50 @@ fn process(
             }
             .unwrap();
 
+            match ys {
+                // fresh mask
+                Some((key, Some(matte))) => {
+                    tracking = true;
+                    assert_eq!(width * height, matte.alpha.len());
+                    write_alpha(&matte.alpha, &mut greyscale);
+                    // worker results are for an older frame, catch them up to this one
+                    if propagate == Propagation::Bbox {
+                        match matte.bounds() {
+                            Some(bbox) => motion.update(key, &bbox),
+                            None => motion.reset(),
+                        }
+                        if key != idx {
+                            translate(&matte.alpha, motion.offset(idx), &mut shifted);
+                            write_alpha(&shifted, &mut greyscale);
+                        }
+                        keyframe = Some(matte.alpha);
+                    }
+                    if use_flow && key != idx {
+                        flow.warp_tracked(&mut greyscale).unwrap();
+                    }
+                }
+                Some((_, None)) => {
+                    //println!("No person found");
+                    tracking = false;
+                    keyframe = None;
+                    motion.reset();
+                    continue;
+                }
+                // nothing to propagate yet
+                None if !tracking => continue,
+                // in between inferences, move the last mask along
+                None => match propagate {
+                    Propagation::None => {}
+                    Propagation::Flow => flow.warp(&mut greyscale).unwrap(),
+                    Propagation::Bbox => {
+                        if let Some(keyframe) = &keyframe {
+                            translate(keyframe, motion.offset(idx), &mut shifted);
+                            write_alpha(&shifted, &mut greyscale);
+                        }
+                    }
+                },
+            }
+
             let start = Instant::now();
             opencv::imgproc::cvt_color(&greyscale, &mut mask_rgba, COLOR_GRAY2RGBA, 0).unwrap();
             //println!("cvt grayscale -> rgba took: {:?}", start.elapsed());
@@ -296,3 +648,108 @@ fn process(
         }
     })
 }
+
+fn bench(args: Args, opts: BenchArgs) -> Result<(), Box<dyn std::error::Error>> {
+    // the `process` loop on one frame over and over, without the v4l devices
+    use opencv::{core::*, imgproc::*};
+
+    if args.model.is_empty() {
+        return Err("`bench` needs `--model` or `--model-name`".into());
+    }
+    let frame = match &opts.input {
+        Some(path) => image::open(path)?.to_rgba8(),
+        None => synthetic_frame(opts.frame_width, opts.frame_height),
+    };
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    let size = Size {
+        width: width as i32,
+        height: height as i32,
+    };
+
+    let mut model = build_segmenter(args)?;
+    model.summary(); // model info
+
+    // the camera delivers MJPG, so every frame starts out as a jpeg
+    let mut compressor = Compressor::new()?;
+    let mut decompressor = Decompressor::new()?;
+    let mut jpeg_buf = OutputBuf::new_owned();
+    compressor.compress(
+        turbojpeg::Image {
+            pixels: frame.as_raw().as_slice(),
+            width,
+            pitch: 4 * width,
+            height,
+            format: turbojpeg::PixelFormat::RGBA,
+        },
+        &mut jpeg_buf,
+    )?;
+    let jpeg = jpeg_buf.to_vec();
+
+    let mut rgba = Mat::new_size_with_default(size, CV_8UC4, Scalar::all(0.0))?;
+    let mut greyscale = Mat::new_size_with_default(size, CV_8UC1, Scalar::all(0.0))?;
+    let mut mask_rgba = Mat::new_size_with_default(size, CV_8UC4, Scalar::all(0.0))?;
+    let mut masked = Mat::new_size_with_default(size, CV_8UC4, Scalar::all(0.0))?;
+
+    let mut bench = Bench::default();
+    for _ in 0..opts.frames {
+        let t_frame = Instant::now();
+
+        let img = bench.time("decode", || -> Result<_, Box<dyn std::error::Error>> {
+            decompressor.decompress(
+                &jpeg,
+                turbojpeg::Image {
+                    pixels: rgba.data_bytes_mut()?,
+                    width,
+                    pitch: 4 * width,
+                    height,
+                    format: turbojpeg::PixelFormat::RGBA,
+                },
+            )?;
+            let rgba8 = image::RgbaImage::from_raw(
+                width as u32,
+                height as u32,
+                rgba.data_bytes()?.to_owned(),
+            )
+            .ok_or("decoded frame has the wrong size")?;
+            Ok(DynamicImage::ImageRgba8(rgba8))
+        })?;
+
+        // preprocess / inference / postprocess as the segmenter splits them
+        let start = Instant::now();
+        let matte = model.segment(&img)?;
+        match model.stage_times() {
+            Some(times) => bench.record_stages(times),
+            None => bench.record("segment", start.elapsed()),
+        }
+
+        bench.time("composite", || -> opencv::Result<()> {
+            // nobody found still composites, with an empty mask
+            match &matte {
+                Some(matte) => write_alpha(&matte.alpha, &mut greyscale),
+                None => greyscale.data_bytes_mut()?.fill(0),
+            }
+            cvt_color(&greyscale, &mut mask_rgba, COLOR_GRAY2RGBA, 0)?;
+            multiply(&mask_rgba, &rgba, &mut masked, 1.0 / 255.0, -1)
+        })?;
+
+        bench.time("encode", || {
+            compressor.compress(
+                turbojpeg::Image {
+                    pixels: masked.data_bytes()?,
+                    width,
+                    pitch: 4 * width,
+                    height,
+                    format: turbojpeg::PixelFormat::RGBA,
+                },
+                &mut jpeg_buf,
+            )?;
+            Ok::<_, Box<dyn std::error::Error>>(())
+        })?;
+
+        bench.record("total", t_frame.elapsed());
+    }
+
+    print!("{}", bench.report(opts.format));
+    end_profiling(model.as_ref());
+    Ok(())
+}
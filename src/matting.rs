@@ -0,0 +1,182 @@
+use anyhow::{bail, Result};
+use image::{DynamicImage, GenericImageView};
+use ndarray::{Array, Ix2, IxDyn};
+
+use crate::{
+    unletterbox, Args, InputSpec, InputTensor, Letterbox, Mask, Matte, OrtBackend, OrtConfig,
+    Resizer, Segmenter, StageTimes,
+};
+
+pub struct PortraitMatting {
+    // single-person matting models (MediaPipe selfie segmentation, MODNet, ...)
+    // input: one frame, output: one [1, 1, h, w] / [1, h, w, 1] / [1, h, w] alpha matte
+    engine: OrtBackend,
+    height: u32,
+    width: u32,
+    mask_threshold: Option<f32>,
+    profile: bool,
+    times: StageTimes,
+    center: bool,
+    pad_color: [u8; 3],
+    spec: InputSpec,
+    xs: InputTensor,
+    resizer: Resizer,
+}
+
+impl PortraitMatting {
+    pub fn new(config: Args) -> Result<Self> {
+        let engine = OrtBackend::build(OrtConfig::from_args(&config))?;
+        let (height, width) = (engine.height(), engine.width());
+
+        // letterbox and input normalisation: cli > metadata > defaults
+        let pad_color = config.pad_rgb()?;
+        let spec = InputSpec::from_metadata(|key| engine.fetch_from_metadata(key))
+            .with_layout(engine.layout())
+            .with_args(&config)?;
+        let xs = InputTensor::zeros(
+            engine.dtype(),
+            &spec.layout.shape(1, height as usize, width as usize),
+        )?;
+
+        Ok(Self {
+            engine,
+            height,
+            width,
+            mask_threshold: config.mask_threshold,
+            profile: config.profile,
+            times: StageTimes::default(),
+            center: config.center,
+            pad_color,
+            xs,
+            spec,
+            resizer: Resizer::new(config.resize),
+        })
+    }
+
+    pub fn letterbox(&self, w0: u32, h0: u32) -> Letterbox {
+        Letterbox::new((w0, h0), (self.width, self.height), self.center)
+    }
+
+    pub fn preprocess(&mut self, img: &DynamicImage) -> Result<()> {
+        self.xs.letterbox(
+            std::slice::from_ref(img),
+            (self.width as usize, self.height as usize),
+            &self.spec,
+            self.pad_color,
+            self.center,
+            &mut self.resizer,
+        );
+        Ok(())
+    }
+
+    pub fn postprocess(&self, xs: &[Array<f32, IxDyn>], xs0: &DynamicImage) -> Result<Matte> {
+        // squeeze the singleton batch/channel axes
+        let alpha = &xs[0];
+        let dims: Vec<usize> = alpha.shape().iter().copied().filter(|&x| x != 1).collect();
+        let &[nh, nw] = &dims[..] else {
+            bail!(
+                "Expected a single-channel alpha matte, got shape {:?}",
+                alpha.shape()
+            );
+        };
+        let alpha = alpha
+            .view()
+            .into_shape((nh, nw))?
+            .into_dimensionality::<Ix2>()?;
+
+        // matte -> frame, the output may be at a different resolution than the input
+        let (w0, h0) = xs0.dimensions();
+        let mut mask = Mask::new(w0, h0);
+        unletterbox(
+            alpha,
+            (0, 0),
+            (
+                nw as f32 / self.width as f32,
+                nh as f32 / self.height as f32,
+            ),
+            &self.letterbox(w0, h0),
+            (0, 0, w0, h0),
+            &mut mask,
+            self.mask_threshold,
+        );
+
+        Ok(Matte {
+            alpha: mask,
+            bbox: None,
+        })
+    }
+
+    pub fn engine(&self) -> &OrtBackend {
+        &self.engine
+    }
+
+    pub fn spec(&self) -> &InputSpec {
+        &self.spec
+    }
+}
+
+impl Segmenter for PortraitMatting {
+    fn segment(&mut self, img: &DynamicImage) -> Result<Option<Matte>> {
+        self.times = StageTimes::default();
+
+        // pre-process
+        let t_pre = std::time::Instant::now();
+        self.preprocess(img)?;
+        self.times.preprocess += t_pre.elapsed();
+        if self.profile {
+            println!("[Model Preprocess]: {:?}", t_pre.elapsed());
+        }
+
+        // run
+        let t_run = std::time::Instant::now();
+        self.engine.run(self.xs.view(), self.profile)?;
+        self.times.inference += t_run.elapsed();
+        if self.profile {
+            println!("[Model Inference]: {:?}", t_run.elapsed());
+        }
+
+        // post-process
+        let t_post = std::time::Instant::now();
+        let ys = self.postprocess(self.engine.outputs(), img)?;
+        self.times.postprocess += t_post.elapsed();
+        if self.profile {
+            println!("[Model Postprocess]: {:?}", t_post.elapsed());
+        }
+
+        Ok(Some(ys))
+    }
+
+    fn stage_times(&self) -> Option<StageTimes> {
+        Some(self.times)
+    }
+
+    fn end_profiling(&self) -> Result<Option<String>> {
+        self.engine.end_profiling()
+    }
+
+    fn summary(&self) {
+        println!(
+            "\nSummary:\n\
+            > Portrait matting\n\
+            > EP: {:?}{}\n\
+            > Dtype: {:?}\n\
+            > Height: {}, Width: {}, Layout: {:?}\n\
+            > Input: {:?} {:?}, mean: {:?}, std: {:?}\n\
+            ",
+            self.engine.ep(),
+            if self.engine.ep_confirmed() {
+                ""
+            } else {
+                " (unconfirmed)"
+            },
+            self.engine.dtype(),
+            self.height,
+            self.width,
+            self.spec.layout,
+            self.spec.order,
+            self.spec.range,
+            self.spec.mean,
+            self.spec.std,
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::{bail, Result};
+use ndarray::{Array, IxDyn};
+
+use crate::{DType, InferenceBackend, Layout, OrtEP, TensorView};
+
+#[derive(Debug, Clone)]
+pub struct MockBackend {
+    // deterministic stand-in for tests: fixed input geometry, scripted outputs per run,
+    // the last script repeats once the queue is down to one
+    dtype: DType,
+    layout: Layout,
+    batch: Option<u32>,
+    size: (u32, u32),
+    metadata: HashMap<String, String>,
+    script: VecDeque<Vec<Array<f32, IxDyn>>>,
+    outputs: Vec<Array<f32, IxDyn>>,
+    inputs: Vec<Vec<usize>>,
+    ep: OrtEP,
+}
+
+impl MockBackend {
+    pub fn new(height: u32, width: u32) -> Self {
+        Self {
+            dtype: DType::Float32,
+            layout: Layout::Nchw,
+            batch: None,
+            size: (height, width),
+            metadata: HashMap::new(),
+            script: VecDeque::new(),
+            outputs: Vec::new(),
+            inputs: Vec::new(),
+            ep: OrtEP::Cpu,
+        }
+    }
+
+    pub fn with_dtype(mut self, dtype: DType) -> Self {
+        self.dtype = dtype;
+        self
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_static_batch(mut self, batch: u32) -> Self {
+        self.batch = Some(batch);
+        self
+    }
+
+    pub fn with_metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_outputs(mut self, ys: Vec<Array<f32, IxDyn>>) -> Self {
+        self.script.push_back(ys);
+        self
+    }
+
+    pub fn runs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn input_shapes(&self) -> &[Vec<usize>] {
+        // shape fed to each run so far
+        &self.inputs
+    }
+}
+
+impl InferenceBackend for MockBackend {
+    fn dtype(&self) -> DType {
+        self.dtype
+    }
+
+    fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    fn batch(&self) -> u32 {
+        self.batch.unwrap_or(1)
+    }
+
+    fn height(&self) -> u32 {
+        self.size.0
+    }
+
+    fn width(&self) -> u32 {
+        self.size.1
+    }
+
+    fn is_batch_dynamic(&self) -> bool {
+        self.batch.is_none()
+    }
+
+    fn is_height_dynamic(&self) -> bool {
+        false
+    }
+
+    fn is_width_dynamic(&self) -> bool {
+        false
+    }
+
+    fn output_shapes(&self) -> Vec<Vec<i32>> {
+        // batch axis as dynamic, the rest from the first scripted run
+        let ys = self.script.front().unwrap_or(&self.outputs);
+        ys.iter()
+            .map(|y| {
+                let mut shape: Vec<i32> = y.shape().iter().map(|&x| x as i32).collect();
+                if self.batch.is_none() {
+                    shape[0] = -1;
+                }
+                shape
+            })
+            .collect()
+    }
+
+    fn fetch_from_metadata(&self, key: &str) -> Option<String> {
+        self.metadata.get(key).cloned()
+    }
+
+    fn ep(&self) -> &OrtEP {
+        &self.ep
+    }
+
+    fn run(&mut self, xs: TensorView, _profile: bool) -> Result<()> {
+        let shape = match &xs {
+            TensorView::F32(x) => x.shape(),
+            TensorView::F16(x) => x.shape(),
+            TensorView::U8(x) => x.shape(),
+            TensorView::I8(x) => x.shape(),
+        };
+        self.inputs.push(shape.to_vec());
+        self.outputs = match self.script.len() {
+            0 => bail!("MockBackend has no scripted outputs"),
+            1 => self.script[0].clone(),
+            _ => self.script.pop_front().unwrap(),
+        };
+        Ok(())
+    }
+
+    fn outputs(&self) -> &[Array<f32, IxDyn>] {
+        &self.outputs
+    }
+}
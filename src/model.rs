@@ -1,67 +1,135 @@
 #![allow(clippy::type_complexity)]
 
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
 use anyhow::Result;
-use image::{DynamicImage, GenericImageView, ImageBuffer};
-use ndarray::{s, Array, Axis, IxDyn};
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use ndarray::{s, Array, Array2, ArrayView3, Axis, Ix2, IxDyn};
 
 use crate::{
-    non_max_suppression, Args, Batch, Bbox, OrtBackend, OrtConfig, OrtEP, Point2, YOLOResult,
+    build_backend, non_max_suppression_with, read_names, unletterbox, Args, Bbox, Error,
+    InferenceBackend, InputSpec, InputTensor, Letterbox, Mask, NmsConfig, OutputLayout, Point2,
+    Resizer, StageTimes, YOLOResult,
 };
 
 pub struct YOLOv8 {
     // YOLOv8 model for all yolo-tasks
-    engine: OrtBackend,
+    engine: Box<dyn InferenceBackend>,
     nc: u32,
     nk: u32,
     nm: u32,
+    output_layout: OutputLayout,
     height: u32,
     width: u32,
     batch: u32,
     conf: f32,
     kconf: f32,
     iou: f32,
-    names: Vec<String>,
+    nms: NmsConfig,
+    mask_threshold: Option<f32>,
+    mask_padding: f32,
+    classes: Option<Vec<usize>>,
+    names: BTreeMap<usize, String>,
     profile: bool,
+    times: StageTimes,
+    center: bool,
+    pad_color: [u8; 3],
+    spec: InputSpec,
+    xs: InputTensor,
+    resizer: Resizer,
 }
 
 impl YOLOv8 {
-    pub fn new(config: Args) -> Result<Self> {
-        // execution provider
-        let ep = if config.trt {
-            OrtEP::Trt(config.device_id)
-        } else if config.cuda {
-            OrtEP::Cuda(config.device_id)
-        } else {
-            OrtEP::Cpu
-        };
-
-        // batch
-        let batch = Batch {
-            opt: config.batch,
-            min: config.batch_min,
-            max: config.batch_max,
-        };
-
-        // build ort engine
-        let ort_args = OrtConfig {
-            ep,
-            batch,
-            f: config.model,
-            trt_fp16: config.fp16,
-            image_size: (config.height, config.width),
-        };
-        let engine = OrtBackend::build(ort_args)?;
+    pub fn new(config: Args) -> Result<Self, Error> {
+        // build engine, ort or tract
+        let engine = build_backend(&config)?;
+        let warmup = config.warmup;
+        let mut model = Self::with_backend(engine, config)?;
+        model.warmup(warmup)?;
+        Ok(model)
+    }
 
+    pub fn with_backend(engine: Box<dyn InferenceBackend>, config: Args) -> Result<Self, Error> {
         //  get batch, height, width, tasks, nc, nk, nm
         let (batch, height, width) = (engine.batch(), engine.height(), engine.width());
-        let nc = engine.nc().or(config.nc).unwrap_or_else(|| {
-            panic!("Failed to get num_classes, make it explicit with `--nc`");
+        let nm = engine.nm();
+        let output_shapes = engine.output_shapes();
+        let output_shape = match output_shapes.first() {
+            Some(shape) if shape.len() == 3 => shape,
+            shape => {
+                return Err(Error::Shape(format!(
+                    "Expected a 3D detection output, got {:?}",
+                    shape
+                )))
+            }
+        };
+        let names = match &config.names {
+            Some(path) => Some(read_names(path)?),
+            None => engine.names()?,
+        };
+        let n_names = names
+            .as_ref()
+            .and_then(|x| x.keys().max())
+            .map(|&id| id as u32 + 1);
+        let output_layout = config.output_layout.unwrap_or_else(|| {
+            OutputLayout::detect(
+                output_shape,
+                nm,
+                n_names.or(config.nc),
+                engine.fetch_from_metadata("description").as_deref(),
+            )
         });
+        let head_nc = output_layout.nc(output_shape, nm);
+        let nc = head_nc
+            .or(config.nc)
+            .or(n_names)
+            .or(output_layout.is_end_to_end().then_some(0)) // class ids are explicit
+            .ok_or_else(|| Error::Metadata {
+                key: "names".to_string(),
+                reason: "Failed to get num_classes, make it explicit with `--nc`".to_string(),
+            })?;
         let nk = 0;
-        let nm = engine.nm();
 
-        // class names
-        let names = engine.names().unwrap_or(vec!["Unknown".to_string()]);
+        // class names, ids the head can't produce are a mismatched names list
+        let names = names.unwrap_or_default();
+        if let Some((&id, name)) = names.iter().find(|(&id, _)| id >= nc as usize) {
+            return Err(Error::Metadata {
+                key: "names".to_string(),
+                reason: format!(
+                    "class id {} ({}) is out of range for a model with {} classes",
+                    id, name, nc
+                ),
+            });
+        }
+
+        // class filter, by name
+        let classes = if config.classes.is_empty() {
+            None
+        } else {
+            let ids = config
+                .classes
+                .iter()
+                .map(|class| {
+                    names
+                        .iter()
+                        .find_map(|(&id, name)| (name == class).then_some(id))
+                        .ok_or_else(|| Error::Config(format!("Unknown class `{}`", class)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Some(ids)
+        };
+
+        // letterbox and input normalisation: cli > metadata > defaults
+        let pad_color = config
+            .pad_rgb()
+            .map_err(|e| Error::Config(format!("{:#}", e)))?;
+        let spec = InputSpec::from_metadata(|key| engine.fetch_from_metadata(key))
+            .with_layout(engine.layout())
+            .with_args(&config)
+            .map_err(|e| Error::Config(format!("{:#}", e)))?;
+        let xs =
+            InputTensor::zeros(engine.dtype(), &[0]).map_err(|e| Error::Dtype(e.to_string()))?;
 
         Ok(Self {
             engine,
@@ -69,13 +137,32 @@ impl YOLOv8 {
             conf: config.conf,
             kconf: config.kconf,
             iou: config.iou,
+            nms: NmsConfig {
+                iou: config.iou,
+                class_aware: config.class_aware,
+                max_det: Some(config.max_det),
+                top_k: config.nms_top_k,
+                soft: config.soft_nms,
+                sigma: config.soft_nms_sigma,
+                score_threshold: config.conf,
+            },
+            mask_threshold: config.mask_threshold,
+            mask_padding: config.mask_padding,
+            classes,
             profile: config.profile,
+            times: StageTimes::default(),
             nc,
             nk,
             nm,
+            output_layout,
             height,
             width,
             batch,
+            center: config.center,
+            pad_color,
+            spec,
+            xs,
+            resizer: Resizer::new(config.resize),
         })
     }
 
@@ -84,201 +171,283 @@ impl YOLOv8 {
         (r, (w0 * r).round(), (h0 * r).round())
     }
 
-    pub fn preprocess(&mut self, img: &DynamicImage) -> Result<Array<f32, IxDyn>> {
-        let mut ys = Array::ones((1, 3, self.height() as usize, self.width() as usize)).into_dyn();
-
-        ys.fill(144.0 / 255.0);
-        let (w0, h0) = img.dimensions();
-        let w0 = w0 as f32;
-        let h0 = h0 as f32;
-        let (_, w_new, h_new) = self.scale_wh(w0, h0, self.width() as f32, self.height() as f32);
+    pub fn letterbox(&self, w0: u32, h0: u32) -> Letterbox {
+        Letterbox::new((w0, h0), (self.width(), self.height()), self.center)
+    }
 
-        let img = img.resize_exact(
-            w_new as u32,
-            h_new as u32,
-            image::imageops::FilterType::CatmullRom,
+    pub fn preprocess(&mut self, xs: &[DynamicImage]) -> Result<()> {
+        // static batch models always take a full batch, the tail is left as padding
+        let bs = if self.engine.is_batch_dynamic() {
+            xs.len()
+        } else {
+            self.batch() as usize
+        };
+        let (height, width) = (self.height() as usize, self.width() as usize);
+        let shape = self.spec.layout.shape(bs, height, width);
+        if self.xs.shape() != shape {
+            self.xs = InputTensor::zeros(self.engine.dtype(), &shape)?;
+        }
+        self.xs.letterbox(
+            xs,
+            (width, height),
+            &self.spec,
+            self.pad_color,
+            self.center,
+            &mut self.resizer,
         );
+        Ok(())
+    }
+
+    pub fn run(&mut self, xs: &DynamicImage) -> Result<Option<YOLOResult>> {
+        Ok(self.run_batch(std::slice::from_ref(xs))?.into_iter().next())
+    }
+
+    pub fn run_batch(&mut self, xs: &[DynamicImage]) -> Result<Vec<YOLOResult>> {
+        // split into chunks of the model batch size, one result per input image
+        let mut ys = Vec::with_capacity(xs.len());
+        self.times = StageTimes::default();
+        for xs in xs.chunks(self.batch().max(1) as usize) {
+            // pre-process
+            let t_pre = std::time::Instant::now();
+            self.preprocess(xs)?;
+            self.times.preprocess += t_pre.elapsed();
+            if self.profile {
+                println!("[Model Preprocess]: {:?}", t_pre.elapsed());
+            }
 
-        for (x, y, rgb) in img.pixels() {
-            let x = x as usize;
-            let y = y as usize;
-            let [r, g, b, _] = rgb.0;
-            ys[[0, 0, y, x]] = (r as f32) / 255.0;
-            ys[[0, 1, y, x]] = (g as f32) / 255.0;
-            ys[[0, 2, y, x]] = (b as f32) / 255.0;
+            // run
+            let t_run = std::time::Instant::now();
+            self.engine.run(self.xs.view(), self.profile)?;
+            self.times.inference += t_run.elapsed();
+            if self.profile {
+                println!("[Model Inference]: {:?}", t_run.elapsed());
+            }
+
+            // post-process
+            let t_post = std::time::Instant::now();
+            ys.extend(self.postprocess(self.engine.outputs(), xs)?);
+            self.times.postprocess += t_post.elapsed();
+            if self.profile {
+                println!("[Model Postprocess]: {:?}", t_post.elapsed());
+            }
         }
 
         Ok(ys)
     }
 
-    pub fn run(&mut self, xs: &DynamicImage) -> Result<Option<YOLOResult>> {
-        // pre-process
-        let t_pre = std::time::Instant::now();
-        let xs_ = self.preprocess(xs).unwrap();
-        if self.profile {
-            println!("[Model Preprocess]: {:?}", t_pre.elapsed());
+    pub fn warmup(&mut self, n: usize) -> Result<Vec<Duration>, Error> {
+        // the first runs pay for allocator and graph warm-up, get them out of the way on a
+        // blank frame and check the head on the way, instead of stuttering the stream
+        let error = |e: anyhow::Error| Error::Warmup(format!("{:#}", e));
+        let x = DynamicImage::ImageRgb8(RgbImage::from_pixel(
+            self.width(),
+            self.height(),
+            Rgb(self.pad_color),
+        ));
+        let mut ts = Vec::with_capacity(n);
+        for _ in 0..n {
+            let t = Instant::now();
+            self.preprocess(std::slice::from_ref(&x)).map_err(error)?;
+            self.engine.run(self.xs.view(), false).map_err(error)?;
+            self.check_outputs()?;
+            self.postprocess(self.engine.outputs(), std::slice::from_ref(&x))
+                .map_err(error)?;
+            ts.push(t.elapsed());
         }
-
-        // run
-        let t_run = std::time::Instant::now();
-        let ys = self.engine.run(xs_, self.profile)?;
-        if self.profile {
-            println!("[Model Inference]: {:?}", t_run.elapsed());
+        if let (Some(first), Some(last)) = (ts.first(), ts.last()) {
+            println!(
+                "[Warm-up]: {} runs in {:?}, first {:?}, last {:?}",
+                n,
+                ts.iter().sum::<Duration>(),
+                first,
+                last
+            );
         }
+        Ok(ts)
+    }
 
-        // post-process
-        let t_post = std::time::Instant::now();
-        let ys = self.postprocess(ys, xs)?;
-        if self.profile {
-            println!("[Model Postprocess]: {:?}", t_post.elapsed());
+    pub fn check_outputs(&self) -> Result<(), Error> {
+        // last run's outputs against what nc / nm and the output layout make of them
+        let ys = self.engine.outputs();
+        let (nc, nm) = (self.nc() as usize, self.nm() as usize);
+        let (axis, len) = match self.output_layout {
+            OutputLayout::V8 => (1, 4 + nc + nm),
+            OutputLayout::V5 => (2, 5 + nc + nm),
+            OutputLayout::E2e => (2, 6 + nm),
+        };
+        let preds = ys.first().map(|x| x.shape());
+        if !matches!(preds, Some(shape) if shape.len() == 3 && shape[axis] == len) {
+            return Err(Error::Shape(format!(
+                "Output 0 is {:?}, expected axis {} to be {} for a {:?} head with nc: {}, nm: {}",
+                preds, axis, len, self.output_layout, nc, nm
+            )));
         }
-
-        Ok(ys)
+        if nm > 0 {
+            let protos = ys.get(1).map(|x| x.shape());
+            if !matches!(protos, Some(&[_, c, _, _]) if c == nm) {
+                return Err(Error::Shape(format!(
+                    "Mask prototypes are {:?}, expected [bs, {}, h, w]",
+                    protos, nm
+                )));
+            }
+        }
+        Ok(())
     }
 
     pub fn postprocess(
         &self,
-        xs: Vec<Array<f32, IxDyn>>,
-        xs0: &DynamicImage,
-    ) -> Result<Option<YOLOResult>> {
-        const CXYWH_OFFSET: usize = 4; // cxcywh
+        xs: &[Array<f32, IxDyn>],
+        xs0: &[DynamicImage],
+    ) -> Result<Vec<YOLOResult>> {
         let preds = &xs[0];
-        let protos = {
-            if xs.len() > 1 {
-                Some(&xs[1])
-            } else {
-                None
+        let protos = xs.get(1);
+
+        // [bs, 4 + nc + nm, anchors] or another `OutputLayout`
+        let (nc, nm) = (self.nc() as usize, self.nm() as usize);
+        let mut ys = Vec::with_capacity(xs0.len());
+        for (idx, (anchor, xs0)) in preds.axis_iter(Axis(0)).zip(xs0).enumerate() {
+            let width_original = xs0.width() as f32;
+            let height_original = xs0.height() as f32;
+            let lb = self.letterbox(xs0.width(), xs0.height());
+            let anchor = anchor.into_dimensionality::<Ix2>()?;
+
+            // save each result
+            let mut data: Vec<(Bbox, Option<Vec<Point2>>, Option<Vec<f32>>)> = Vec::new();
+            for pred in self.output_layout.anchors(anchor).axis_iter(Axis(0)) {
+                // split preds for different tasks
+                let (bbox, id, confidence, coefs) = self.output_layout.decode(pred, nc, nm);
+                // detection-only heads carry no mask coefficients
+                let coefs = (nm > 0 && protos.is_some()).then(|| coefs.to_vec());
+
+                // confidence and class filter
+                if confidence < self.conf
+                    || self.classes.as_ref().is_some_and(|ids| !ids.contains(&id))
+                {
+                    continue;
+                }
+
+                // bbox re-scale, undoing the letterbox padding
+                let (cx, cy) = lb.to_original(bbox[0], bbox[1]);
+                let w = bbox[2] / lb.ratio;
+                let h = bbox[3] / lb.ratio;
+                let x = cx - w / 2.;
+                let y = cy - h / 2.;
+                let y_bbox = Bbox::new(
+                    x.max(0.0f32).min(width_original),
+                    y.max(0.0f32).min(height_original),
+                    w,
+                    h,
+                    id,
+                    confidence,
+                );
+
+                // data merged
+                data.push((y_bbox, None, coefs));
             }
-        };
 
-        let Some((idx, anchor)) = preds.axis_iter(Axis(0)).enumerate().next() else {
-            return Ok(None);
-        };
-        // [bs, 4 + nc + nm, anchors]
-        // input image
-        let width_original = xs0.width() as f32;
-        let height_original = xs0.height() as f32;
-        let ratio =
-            (self.width() as f32 / width_original).min(self.height() as f32 / height_original);
-
-        // save each result
-        let mut data: Vec<(Bbox, Option<Vec<Point2>>, Option<Vec<f32>>)> = Vec::new();
-        for pred in anchor.axis_iter(Axis(1)) {
-            // split preds for different tasks
-            let bbox = pred.slice(s![0..CXYWH_OFFSET]);
-            let clss = pred.slice(s![CXYWH_OFFSET..CXYWH_OFFSET + self.nc() as usize]);
-            let coefs = { Some(pred.slice(s![pred.len() - self.nm() as usize..]).to_vec()) };
-
-            // confidence and id
-            let (id, &confidence) = clss
-                .into_iter()
-                .enumerate()
-                .reduce(|max, x| if x.1 > max.1 { x } else { max })
-                .unwrap(); // definitely will not panic!
-
-            // confidence filter
-            if confidence < self.conf {
-                continue;
+            // nms, end-to-end heads already did it
+            if self.output_layout.is_end_to_end() {
+                data.sort_by(|a, b| b.0.confidence.total_cmp(&a.0.confidence));
+                data.truncate(self.nms.max_det.unwrap_or(usize::MAX));
+            } else {
+                non_max_suppression_with(&mut data, &self.nms);
             }
 
-            // bbox re-scale
-            let cx = bbox[0] / ratio;
-            let cy = bbox[1] / ratio;
-            let w = bbox[2] / ratio;
-            let h = bbox[3] / ratio;
-            let x = cx - w / 2.;
-            let y = cy - h / 2.;
-            let y_bbox = Bbox::new(
-                x.max(0.0f32).min(width_original),
-                y.max(0.0f32).min(height_original),
-                w,
-                h,
-                id,
-                confidence,
-            );
+            // decode
+            let mut y_bboxes: Vec<Bbox> = Vec::new();
+            let mut y_kpts: Vec<Vec<Point2>> = Vec::new();
+            let mut masks = Vec::new();
+            for elem in data.into_iter() {
+                if let Some(kpts) = elem.1 {
+                    y_kpts.push(kpts)
+                }
+
+                // decode masks
+                if let (Some(coefs), Some(protos)) = (elem.2, protos) {
+                    let proto = protos.slice(s![idx, .., .., ..]);
+                    masks.push(self.decode_mask(&coefs, proto, &elem.0, &lb, xs0.dimensions()));
+                }
+                y_bboxes.push(elem.0);
+            }
 
-            // data merged
-            data.push((y_bbox, None, coefs));
+            ys.push(YOLOResult {
+                probs: None,
+                bboxes: y_bboxes,
+                keypoints: y_kpts,
+                masks,
+            });
         }
 
-        // nms
-        non_max_suppression(&mut data, self.iou);
+        Ok(ys)
+    }
 
-        // decode
-        let mut y_bboxes: Vec<Bbox> = Vec::new();
-        let mut y_kpts: Vec<Vec<Point2>> = Vec::new();
-        let mut masks = Vec::new();
-        for elem in data.into_iter() {
-            if let Some(kpts) = elem.1 {
-                y_kpts.push(kpts)
-            }
+    pub fn decode_mask(
+        &self,
+        coefs: &[f32],
+        proto: ArrayView3<f32>,
+        bbox: &Bbox,
+        lb: &Letterbox,
+        (w0, h0): (u32, u32),
+    ) -> Mask {
+        let (_, nh, nw) = proto.dim();
+        let mut mask = Mask::new(w0, h0);
+
+        // padded bbox in frame pixels, [x0, x1) x [y0, y1)
+        let x0 = (bbox.xmin() - self.mask_padding).floor().max(0.0) as u32;
+        let y0 = (bbox.ymin() - self.mask_padding).floor().max(0.0) as u32;
+        let x1 = (bbox.xmax() + self.mask_padding).ceil().min(w0 as f32) as u32;
+        let y1 = (bbox.ymax() + self.mask_padding).ceil().min(h0 as f32) as u32;
+        if x1 <= x0 || y1 <= y0 {
+            return mask;
+        }
 
-            // decode masks
-            if let Some(coefs) = elem.2 {
-                let proto = protos.unwrap().slice(s![idx, .., .., ..]);
-                let (nm, nh, nw) = proto.dim();
-
-                // coefs * proto -> mask
-                let coefs = Array::from_shape_vec((1, nm), coefs)?; // (n, nm)
-                let proto = proto.to_owned().into_shape((nm, nh * nw))?; // (nm, nh*nw)
-                let mask = coefs.dot(&proto).into_shape((nh, nw, 1))?; // (nh, nw, n)
-                                                                       // build image from ndarray
-                let mask_im: ImageBuffer<image::Luma<_>, Vec<f32>> =
-                    match ImageBuffer::from_raw(nw as u32, nh as u32, mask.into_raw_vec()) {
-                        Some(image) => image,
-                        None => panic!("can not create image from ndarray"),
-                    };
-                let mut mask_im = image::DynamicImage::from(mask_im); // -> dyn
-
-                // rescale masks
-                let (_, w_mask, h_mask) =
-                    self.scale_wh(width_original, height_original, nw as f32, nh as f32);
-                let mask_cropped = mask_im.crop(0, 0, w_mask as u32, h_mask as u32);
-                let mask_original = mask_cropped.resize_exact(
-                    // resize_to_fill
-                    width_original as u32,
-                    height_original as u32,
-                    image::imageops::FilterType::CatmullRom,
-                );
+        // frame -> proto coordinates, protos cover the whole padded input
+        let (sx, sy) = (
+            nw as f32 / self.width() as f32,
+            nh as f32 / self.height() as f32,
+        );
+        let to_proto = |x: f32, y: f32| {
+            let (x, y) = lb.to_input(x, y);
+            (x * sx, y * sy)
+        };
 
-                // crop-mask with bbox
-                let mut mask_original_cropped = mask_original.into_luma8();
-                for y in 0..height_original as usize {
-                    for x in 0..width_original as usize {
-                        let padding = 10.0;
-                        let xmin = elem.0.xmin - padding;
-                        let xmax = elem.0.xmax() + padding;
-                        let ymin = elem.0.ymin - padding;
-                        let ymax = elem.0.ymax() + padding;
-                        if x < xmin as usize
-                            || x > xmax as usize
-                            || y < ymin as usize
-                            || y > ymax as usize
-                        {
-                            mask_original_cropped.put_pixel(x as u32, y as u32, image::Luma([0u8]));
-                        }
-                    }
-                }
-                masks.push(mask_original_cropped);
-            }
-            y_bboxes.push(elem.0);
+        // proto region under the bbox, one texel of margin for interpolation
+        let (px0, py0) = to_proto(x0 as f32, y0 as f32);
+        let (px1, py1) = to_proto(x1 as f32, y1 as f32);
+        let px0 = (px0.floor() as usize).saturating_sub(1).min(nw - 1);
+        let py0 = (py0.floor() as usize).saturating_sub(1).min(nh - 1);
+        let px1 = (px1.ceil() as usize + 1).clamp(px0 + 1, nw);
+        let py1 = (py1.ceil() as usize + 1).clamp(py0 + 1, nh);
+
+        // coefs * proto over the region only -> probs
+        let region = proto.slice(s![.., py0..py1, px0..px1]);
+        let mut local = Array2::<f32>::zeros((py1 - py0, px1 - px0));
+        for (&c, plane) in coefs.iter().zip(region.outer_iter()) {
+            local.scaled_add(c, &plane);
         }
+        local.mapv_inplace(|x| 1.0 / (1.0 + (-x).exp()));
+
+        unletterbox(
+            local.view(),
+            (px0, py0),
+            (sx, sy),
+            lb,
+            (x0, y0, x1, y1),
+            &mut mask,
+            self.mask_threshold,
+        );
 
-        Ok(Some(YOLOResult {
-            probs: None,
-            bboxes: y_bboxes,
-            keypoints: y_kpts,
-            masks,
-        }))
+        mask
     }
 
     pub fn summary(&self) {
         println!(
             "\nSummary:\n\
             > Author: {:?}\n
-            > EP: {:?} {}\n\
+            > EP: {:?}{}\n\
             > Dtype: {:?}\n\
             > Batch: {} ({}), Height: {} ({}), Width: {} ({})\n\
+            > Output: {:?}\n\
             > nc: {} nk: {}, nm: {}, conf: {}, kconf: {}, iou: {}\n\
             ",
             match self.engine.author().zip(self.engine.version()) {
@@ -286,10 +455,10 @@ impl YOLOv8 {
                 None => String::from(""),
             },
             self.engine.ep(),
-            if let OrtEP::Cpu = self.engine.ep() {
+            if self.engine.ep_confirmed() {
                 ""
             } else {
-                "(May still fall back to CPU)"
+                " (unconfirmed)"
             },
             self.engine.dtype(),
             self.batch(),
@@ -310,6 +479,7 @@ impl YOLOv8 {
             } else {
                 "Const"
             },
+            self.output_layout(),
             self.nc(),
             self.nk(),
             self.nm(),
@@ -319,8 +489,17 @@ impl YOLOv8 {
         );
     }
 
-    pub fn engine(&self) -> &OrtBackend {
-        &self.engine
+    pub fn stage_times(&self) -> StageTimes {
+        // summed over the batches of the last `run_batch`
+        self.times
+    }
+
+    pub fn spec(&self) -> &InputSpec {
+        &self.spec
+    }
+
+    pub fn engine(&self) -> &dyn InferenceBackend {
+        self.engine.as_ref()
     }
 
     pub fn conf(&self) -> f32 {
@@ -343,6 +522,18 @@ impl YOLOv8 {
         self.iou
     }
 
+    pub fn nms(&self) -> &NmsConfig {
+        &self.nms
+    }
+
+    pub fn mask_threshold(&self) -> Option<f32> {
+        self.mask_threshold
+    }
+
+    pub fn mask_padding(&self) -> f32 {
+        self.mask_padding
+    }
+
     pub fn batch(&self) -> u32 {
         self.batch
     }
@@ -367,7 +558,11 @@ impl YOLOv8 {
         self.nm
     }
 
-    pub fn names(&self) -> &Vec<String> {
+    pub fn output_layout(&self) -> OutputLayout {
+        self.output_layout
+    }
+
+    pub fn names(&self) -> &BTreeMap<usize, String> {
         &self.names
     }
 }
@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+
+use crate::Error;
+
+pub fn parse_names(s: &str) -> Result<BTreeMap<usize, String>, String> {
+    // class names as written by exporters:
+    // python dict `{0: 'person', 1: "yellow_lady's_slipper"}`, JSON `{"0": "person"}`,
+    // or a list of either quote style where the position is the id
+    let mut p = Parser {
+        chars: s.chars().collect(),
+        pos: 0,
+    };
+    let names = match p.peek() {
+        Some('{') => p.dict()?,
+        Some('[') => p.list()?,
+        _ => return Err(p.error("expected `{` or `[`")),
+    };
+    match p.peek() {
+        None => Ok(names),
+        Some(_) => Err(p.error("trailing characters")),
+    }
+}
+
+pub fn read_names(path: &str) -> Result<BTreeMap<usize, String>, Error> {
+    // `--names` file: a dict or list literal as above, or one name per line
+    let error = |reason: String| Error::Config(format!("Bad names file `{}`: {}", path, reason));
+    let s = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    match s.trim_start().chars().next() {
+        Some('{' | '[') => parse_names(&s).map_err(error),
+        _ => Ok(s
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .enumerate()
+            .collect()),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<char> {
+        // next non-whitespace char, not consumed
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let ok = self.peek() == Some(c);
+        if ok {
+            self.pos += 1;
+        }
+        ok
+    }
+
+    fn error(&self, reason: &str) -> String {
+        format!("{} at char {}", reason, self.pos)
+    }
+
+    fn dict(&mut self) -> Result<BTreeMap<usize, String>, String> {
+        self.eat('{');
+        let mut names = BTreeMap::new();
+        while !self.eat('}') {
+            let at = self.pos;
+            let id = match self.peek() {
+                Some('\'' | '"') => self.string()?,
+                _ => self.integer(),
+            };
+            let id = id
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("class id `{}` is not an integer at char {}", id, at))?;
+            if !self.eat(':') {
+                return Err(self.error("expected `:`"));
+            }
+            let name = self.string()?;
+            if names.insert(id, name).is_some() {
+                return Err(format!("duplicate class id {} at char {}", id, at));
+            }
+            if !self.eat(',') && self.peek() != Some('}') {
+                return Err(self.error("expected `,` or `}`"));
+            }
+        }
+        Ok(names)
+    }
+
+    fn list(&mut self) -> Result<BTreeMap<usize, String>, String> {
+        self.eat('[');
+        let mut names = BTreeMap::new();
+        while !self.eat(']') {
+            names.insert(names.len(), self.string()?);
+            if !self.eat(',') && self.peek() != Some(']') {
+                return Err(self.error("expected `,` or `]`"));
+            }
+        }
+        Ok(names)
+    }
+
+    fn integer(&mut self) -> String {
+        self.peek();
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        // single or double quoted, with python / JSON escapes
+        let quote = match self.peek() {
+            Some(q @ ('\'' | '"')) => q,
+            _ => return Err(self.error("expected a quoted string")),
+        };
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let Some(&c) = self.chars.get(self.pos) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+            match c {
+                c if c == quote => return Ok(s),
+                '\\' => {
+                    let Some(&e) = self.chars.get(self.pos) else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.pos += 1;
+                    match e {
+                        'n' => s.push('\n'),
+                        't' => s.push('\t'),
+                        'r' => s.push('\r'),
+                        'u' | 'x' => {
+                            let n = if e == 'u' { 4 } else { 2 };
+                            let hex: String = self.chars.iter().skip(self.pos).take(n).collect();
+                            let c = u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .filter(|_| hex.len() == n)
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("bad escape"))?;
+                            self.pos += n;
+                            s.push(c);
+                        }
+                        // `\'`, `\"`, `\\`, `\/`, anything else is kept as is
+                        e => s.push(e),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use clap::ValueEnum;
+
+use crate::{Bbox, Point2};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SoftNms {
+    // score decay applied to overlapping boxes instead of dropping them
+    Linear,
+    Gaussian,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NmsConfig {
+    // NMS options, the defaults match the original class-agnostic greedy NMS
+    pub iou: f32,
+    pub class_aware: bool,
+    pub max_det: Option<usize>,
+    pub top_k: Option<usize>,
+    pub soft: Option<SoftNms>,
+    pub sigma: f32,
+    pub score_threshold: f32,
+}
+
+impl Default for NmsConfig {
+    fn default() -> Self {
+        Self {
+            iou: 0.45,
+            class_aware: false,
+            max_det: None,
+            top_k: None,
+            soft: None,
+            sigma: 0.5,
+            score_threshold: 0.0,
+        }
+    }
+}
+
+impl NmsConfig {
+    pub fn new(iou: f32) -> Self {
+        Self {
+            iou,
+            ..Default::default()
+        }
+    }
+
+    fn overlaps(&self, a: &Bbox, b: &Bbox) -> Option<f32> {
+        // iou between two boxes that are allowed to suppress each other
+        if self.class_aware && a.id != b.id {
+            None
+        } else {
+            Some(a.iou(b))
+        }
+    }
+}
+
+pub fn non_max_suppression(
+    xs: &mut Vec<(Bbox, Option<Vec<Point2>>, Option<Vec<f32>>)>,
+    iou_threshold: f32,
+) {
+    non_max_suppression_with(xs, &NmsConfig::new(iou_threshold))
+}
+
+pub fn non_max_suppression_with(
+    xs: &mut Vec<(Bbox, Option<Vec<Point2>>, Option<Vec<f32>>)>,
+    config: &NmsConfig,
+) {
+    // NaN confidences can't be ranked, drop them instead of panicking
+    xs.retain(|x| !x.0.confidence.is_nan());
+    xs.sort_by(|b1, b2| b2.0.confidence.total_cmp(&b1.0.confidence));
+    if let Some(top_k) = config.top_k {
+        xs.truncate(top_k);
+    }
+    let max_det = config.max_det.unwrap_or(usize::MAX);
+
+    match config.soft {
+        None => {
+            let mut current_index = 0;
+            for index in 0..xs.len() {
+                if current_index >= max_det {
+                    break;
+                }
+                let mut drop = false;
+                for prev_index in 0..current_index {
+                    match config.overlaps(&xs[prev_index].0, &xs[index].0) {
+                        Some(iou) if iou > config.iou => {
+                            drop = true;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                if !drop {
+                    xs.swap(current_index, index);
+                    current_index += 1;
+                }
+            }
+            xs.truncate(current_index);
+        }
+        Some(method) => {
+            // pick the best remaining box, decay the rest, repeat
+            let mut current_index = 0;
+            while current_index < xs.len() && current_index < max_det {
+                let best = (current_index..xs.len())
+                    .max_by(|&a, &b| xs[a].0.confidence.total_cmp(&xs[b].0.confidence))
+                    .unwrap(); // non-empty range
+                xs.swap(current_index, best);
+
+                let mut index = current_index + 1;
+                while index < xs.len() {
+                    if let Some(iou) = config.overlaps(&xs[current_index].0, &xs[index].0) {
+                        let weight = match method {
+                            SoftNms::Linear if iou > config.iou => 1.0 - iou,
+                            SoftNms::Linear => 1.0,
+                            SoftNms::Gaussian => (-(iou * iou) / config.sigma).exp(),
+                        };
+                        xs[index].0.confidence *= weight;
+                    }
+                    if xs[index].0.confidence < config.score_threshold {
+                        xs.swap_remove(index);
+                    } else {
+                        index += 1;
+                    }
+                }
+                current_index += 1;
+            }
+            xs.truncate(current_index);
+        }
+    }
+}
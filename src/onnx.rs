@@ -0,0 +1,253 @@
+use std::collections::HashSet;
+
+use crate::{Batch, DType, Error, Layout, OrtConfig};
+
+// Just enough of the ONNX protobuf to see a model's inputs and metadata without a session.
+// Field numbers are from onnx.proto: ModelProto, GraphProto, ValueInfoProto, TypeProto.
+
+#[derive(Debug, Clone, Default)]
+pub struct ModelInfo {
+    // graph inputs that are not initializers: (name, elem_type, dims with -1 for symbolic)
+    pub inputs: Vec<(String, Option<DType>, Vec<i32>)>,
+    pub metadata: Vec<(String, String)>,
+}
+
+impl ModelInfo {
+    pub fn read(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        Self::parse(&bytes).ok_or_else(|| "not a valid ONNX model".to_string())
+    }
+
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut graph = None;
+        let mut metadata = Vec::new();
+        for field in Fields(bytes) {
+            match field? {
+                (7, Wire::Bytes(x)) => graph = Some(x),
+                (14, Wire::Bytes(x)) => {
+                    let (mut key, mut value) = (String::new(), String::new());
+                    for field in Fields(x) {
+                        match field? {
+                            (1, Wire::Bytes(x)) => key = String::from_utf8_lossy(x).into(),
+                            (2, Wire::Bytes(x)) => value = String::from_utf8_lossy(x).into(),
+                            _ => {}
+                        }
+                    }
+                    metadata.push((key, value));
+                }
+                _ => {}
+            }
+        }
+
+        // older exporters list initializers among the graph inputs too
+        let mut inputs = Vec::new();
+        let mut initializers = HashSet::new();
+        for field in Fields(graph?) {
+            match field? {
+                (11, Wire::Bytes(x)) => inputs.push(value_info(x)?),
+                (5, Wire::Bytes(x)) => {
+                    for field in Fields(x) {
+                        if let (8, Wire::Bytes(name)) = field? {
+                            initializers.insert(String::from_utf8_lossy(name).into_owned());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        inputs.retain(|(name, ..)| !initializers.contains(name));
+        Some(Self { inputs, metadata })
+    }
+
+    pub fn metadata(&self, key: &str) -> Option<String> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ModelInputs {
+    // ONNX model inputs attrs, `sizes` is filled in by `resolve`
+    pub shapes: Vec<Vec<i32>>,
+    pub dtypes: Vec<DType>,
+    pub names: Vec<String>,
+    pub sizes: Vec<Vec<u32>>,
+}
+
+impl ModelInputs {
+    pub fn from_model(model: &ModelInfo) -> Self {
+        // read from the ONNX file, unknown dtypes are left to the runtime to reject
+        let mut x = Self::default();
+        for (name, dtype, shape) in model.inputs.iter() {
+            x.names.push(name.clone());
+            x.dtypes.push(dtype.unwrap_or(DType::Float32));
+            x.shapes.push(shape.clone());
+        }
+        x
+    }
+
+    pub fn resolve(
+        mut self,
+        args: &OrtConfig,
+        metadata: impl Fn(&str) -> Option<String>,
+    ) -> Result<(Self, Batch, Layout), Error> {
+        // batch size, layout and image size of the first input, from the model or the config
+        let shape = match self.shapes.first() {
+            Some(shape) if shape.len() == 4 => shape.clone(),
+            Some(shape) => {
+                return Err(Error::Shape(format!(
+                    "Expected a 4D image input, got shape {:?}",
+                    shape
+                )))
+            }
+            None => return Err(Error::Shape("Model has no inputs".to_string())),
+        };
+
+        // batch size
+        let mut batch = args.batch.clone();
+        if shape[0] != -1 {
+            if shape[0] as u32 != batch.opt {
+                return Err(Error::Shape(format!(
+                    "Expected batch size: {}, got {}. Try using `--batch {}`.",
+                    shape[0], batch.opt, shape[0]
+                )));
+            }
+            batch.opt = shape[0] as u32;
+        }
+
+        // layout: explicit, from metadata, or guessed from the input shape
+        let layout = args
+            .layout
+            .or_else(|| clap::ValueEnum::from_str(metadata("layout")?.trim(), true).ok())
+            .or_else(|| Layout::detect(&shape))
+            .unwrap_or_default();
+        let (h_axis, w_axis) = layout.hw_axes();
+
+        // input size: height and width
+        let height = match (shape[h_axis], args.image_size.0) {
+            (-1, Some(height)) => height,
+            (-1, None) => {
+                return Err(Error::Shape(
+                    "Failed to get model height. Make it explicit with `--height`".to_string(),
+                ))
+            }
+            (height, _) => height as u32,
+        };
+        let width = match (shape[w_axis], args.image_size.1) {
+            (-1, Some(width)) => width,
+            (-1, None) => {
+                return Err(Error::Shape(
+                    "Failed to get model width. Make it explicit with `--width`".to_string(),
+                ))
+            }
+            (width, _) => width as u32,
+        };
+        self.sizes.push(vec![height, width]);
+        Ok((self, batch, layout))
+    }
+}
+
+fn value_info(bytes: &[u8]) -> Option<(String, Option<DType>, Vec<i32>)> {
+    // ValueInfoProto { name: 1, type: 2 { tensor_type: 1 { elem_type: 1, shape: 2 { dim: 1 } } } }
+    let (mut name, mut dtype, mut dims) = (String::new(), None, Vec::new());
+    for field in Fields(bytes) {
+        match field? {
+            (1, Wire::Bytes(x)) => name = String::from_utf8_lossy(x).into(),
+            (2, Wire::Bytes(x)) => {
+                for field in Fields(x) {
+                    let (1, Wire::Bytes(x)) = field? else {
+                        continue;
+                    };
+                    for field in Fields(x) {
+                        match field? {
+                            (1, Wire::Varint(x)) => dtype = DType::from_onnx(x),
+                            (2, Wire::Bytes(x)) => {
+                                for field in Fields(x) {
+                                    let (1, Wire::Bytes(x)) = field? else {
+                                        continue;
+                                    };
+                                    dims.push(dim(x)?);
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Some((name, dtype, dims))
+}
+
+fn dim(bytes: &[u8]) -> Option<i32> {
+    // Dimension { dim_value: 1, dim_param: 2 }, a named or missing dim is dynamic
+    for field in Fields(bytes) {
+        if let (1, Wire::Varint(x)) = field? {
+            return Some(x as i32);
+        }
+    }
+    Some(-1)
+}
+
+enum Wire<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Iterator for Fields<'a> {
+    // (field number, value), `None` inside means the buffer is malformed
+    type Item = Option<(u64, Wire<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_none() {
+            self.0 = &[];
+        }
+        Some(field)
+    }
+}
+
+impl<'a> Fields<'a> {
+    fn field(&mut self) -> Option<(u64, Wire<'a>)> {
+        let key = self.varint()?;
+        let wire = match key & 7 {
+            0 => Wire::Varint(self.varint()?),
+            1 => self.skip(8)?,
+            2 => {
+                let n = usize::try_from(self.varint()?).ok()?;
+                let (x, rest) = (self.0.get(..n)?, &self.0[n..]);
+                self.0 = rest;
+                Wire::Bytes(x)
+            }
+            5 => self.skip(4)?,
+            _ => return None,
+        };
+        Some((key >> 3, wire))
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut x = 0u64;
+        for (i, b) in self.0.iter().enumerate().take(10) {
+            x |= ((b & 0x7f) as u64) << (7 * i);
+            if b & 0x80 == 0 {
+                self.0 = &self.0[i + 1..];
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    fn skip(&mut self, n: usize) -> Option<Wire<'a>> {
+        self.0 = self.0.get(n..)?;
+        Some(Wire::Fixed)
+    }
+}
@@ -1,76 +1,175 @@
-use anyhow::Result;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Result};
 use half::f16;
-use ndarray::{Array, CowArray, IxDyn};
-use ort::execution_providers::{CUDAExecutionProviderOptions, TensorRTExecutionProviderOptions};
+use ndarray::{Array, ArrayView, CowArray, IxDyn};
+use ort::execution_providers::{
+    CPUExecutionProviderOptions, CUDAExecutionProviderOptions, OneDNNExecutionProviderOptions,
+    OpenVINOExecutionProviderOptions, TensorRTExecutionProviderOptions,
+};
 use ort::tensor::TensorElementDataType;
-use ort::{Environment, ExecutionProvider, Session, SessionBuilder, Value};
+use ort::{
+    Environment, ExecutionProvider, GraphOptimizationLevel, LoggingLevel, Session, SessionBuilder,
+    Value,
+};
 use regex::Regex;
-
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub enum OrtEP {
-    // ONNXRuntime execution provider
-    Cpu,
-    Cuda(u32),
-    Trt(u32),
+use tracing::field::Field;
+use tracing::{span, Event, Metadata, Subscriber};
+
+use crate::onnx::{ModelInfo, ModelInputs};
+use crate::{
+    output_quantization, Batch, DType, Error, InferenceBackend, Layout, LogLevel, OptLevel,
+    OrtConfig, OrtEP, Quantization, TensorView,
+};
+
+impl From<OptLevel> for GraphOptimizationLevel {
+    fn from(x: OptLevel) -> Self {
+        match x {
+            OptLevel::Disable => GraphOptimizationLevel::Disable,
+            OptLevel::Basic => GraphOptimizationLevel::Level1,
+            OptLevel::Extended => GraphOptimizationLevel::Level2,
+            OptLevel::All => GraphOptimizationLevel::Level3,
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct Batch {
-    pub opt: u32,
-    pub min: u32,
-    pub max: u32,
+impl From<LogLevel> for LoggingLevel {
+    fn from(x: LogLevel) -> Self {
+        match x {
+            LogLevel::Verbose => LoggingLevel::Verbose,
+            LogLevel::Info => LoggingLevel::Info,
+            LogLevel::Warning => LoggingLevel::Warning,
+            LogLevel::Error => LoggingLevel::Error,
+            LogLevel::Fatal => LoggingLevel::Fatal,
+        }
+    }
 }
 
-impl Default for Batch {
-    fn default() -> Self {
-        Self {
-            opt: 1,
-            min: 1,
-            max: 1,
+impl From<TensorElementDataType> for DType {
+    fn from(x: TensorElementDataType) -> Self {
+        match x {
+            TensorElementDataType::Float32 => DType::Float32,
+            TensorElementDataType::Uint8 => DType::Uint8,
+            TensorElementDataType::Int8 => DType::Int8,
+            TensorElementDataType::Uint16 => DType::Uint16,
+            TensorElementDataType::Int16 => DType::Int16,
+            TensorElementDataType::Int32 => DType::Int32,
+            TensorElementDataType::Int64 => DType::Int64,
+            TensorElementDataType::String => DType::String,
+            TensorElementDataType::Bool => DType::Bool,
+            TensorElementDataType::Float16 => DType::Float16,
+            TensorElementDataType::Float64 => DType::Float64,
+            TensorElementDataType::Uint32 => DType::Uint32,
+            TensorElementDataType::Uint64 => DType::Uint64,
+            TensorElementDataType::Bfloat16 => DType::Bfloat16,
         }
     }
 }
 
+fn session_inputs(session: &Session) -> ModelInputs {
+    // same as `ModelInputs::from_model`, from a built session
+    let mut x = ModelInputs::default();
+    for i in session.inputs.iter() {
+        x.shapes.push(
+            i.dimensions()
+                .map(|x| if let Some(x) = x { x as i32 } else { -1i32 })
+                .collect(),
+        );
+        x.dtypes.push(i.input_type.into());
+        x.names.push(i.name.clone());
+    }
+    x
+}
+
+#[derive(Debug, Clone, Default)]
+struct Registered(Arc<Mutex<Option<String>>>);
+
+impl Registered {
+    fn name(&self) -> Option<String> {
+        // the provider ORT logged as registered, `None` if nothing matched
+        self.0.lock().ok()?.clone()
+    }
+}
+
+impl Subscriber for Registered {
+    // picks "Successfully registered `<provider>`" out of ort's events while a session is built
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target().starts_with("ort")
+    }
+
+    fn new_span(&self, _: &span::Attributes) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event) {
+        event.record(&mut |field: &Field, value: &dyn std::fmt::Debug| {
+            if field.name() != "message" {
+                return;
+            }
+            let message = format!("{:?}", value);
+            if let Some(name) = message
+                .strip_prefix("Successfully registered `")
+                .and_then(|x| x.strip_suffix('`'))
+            {
+                if let Ok(mut x) = self.0.lock() {
+                    *x = Some(name.to_string());
+                }
+            }
+        });
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+enum Input<'a> {
+    // session input, borrowed as is or converted from f32 to f16
+    F32(CowArray<'a, f32, IxDyn>),
+    F16(CowArray<'a, f16, IxDyn>),
+    U8(CowArray<'a, u8, IxDyn>),
+    I8(CowArray<'a, i8, IxDyn>),
+}
+
+fn copy_into<T: Copy>(
+    dst: &mut Array<f32, IxDyn>,
+    src: &ArrayView<T, IxDyn>,
+    f: impl Fn(T) -> f32,
+) {
+    // reuses `dst` unless the shape changed
+    if dst.shape() != src.shape() {
+        *dst = Array::zeros(src.raw_dim());
+    }
+    dst.zip_mut_with(src, |y, &x| *y = f(x));
+}
+
 #[derive(Debug, Default)]
-pub struct OrtInputs {
-    // ONNX model inputs attrs
-    pub shapes: Vec<Vec<i32>>,
-    pub dtypes: Vec<TensorElementDataType>,
-    pub names: Vec<String>,
-    pub sizes: Vec<Vec<u32>>,
+pub struct Recurrent {
+    // recurrent state carried between runs, see `OrtBackend::recurrent`
+    links: Vec<(usize, usize)>,
+    states: Vec<Array<f32, IxDyn>>,
+    shapes: Vec<Vec<usize>>,
 }
 
-impl OrtInputs {
-    pub fn new(session: &Session) -> Self {
-        let mut shapes = Vec::new();
-        let mut dtypes = Vec::new();
-        let mut names = Vec::new();
-        for i in session.inputs.iter() {
-            let shape: Vec<i32> = i
-                .dimensions()
-                .map(|x| if let Some(x) = x { x as i32 } else { -1i32 })
-                .collect();
-            shapes.push(shape);
-            dtypes.push(i.input_type);
-            names.push(i.name.clone());
-        }
-        Self {
-            shapes,
-            dtypes,
-            names,
-            ..Default::default()
+impl Recurrent {
+    pub fn reset(&mut self) {
+        // back to the initial zero state, e.g. on scene cuts
+        for (x, shape) in self.states.iter_mut().zip(&self.shapes) {
+            if x.shape() == shape.as_slice() {
+                x.fill(0.0);
+            } else {
+                *x = Array::zeros(IxDyn(shape));
+            }
         }
     }
-}
 
-#[derive(Debug)]
-pub struct OrtConfig {
-    // ORT config
-    pub f: String,
-    pub ep: OrtEP,
-    pub trt_fp16: bool,
-    pub batch: Batch,
-    pub image_size: (Option<u32>, Option<u32>),
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
 }
 
 #[derive(Debug)]
@@ -78,96 +177,135 @@ pub struct OrtBackend {
     // ORT engine
     session: Session,
     ep: OrtEP,
+    ep_confirmed: bool,
     batch: Batch,
-    inputs: OrtInputs,
+    inputs: ModelInputs,
+    layout: Layout,
+    dequant: Vec<Quantization>,
+    f16_inputs: Vec<Array<f16, IxDyn>>,
+    outputs: Vec<Array<f32, IxDyn>>,
+    profiling: bool,
 }
 
 impl OrtBackend {
-    pub fn build(args: OrtConfig) -> Result<Self> {
-        // build env & session
+    pub fn build(args: OrtConfig) -> Result<Self, Error> {
+        let load = |reason: String| Error::Load {
+            path: args.f.clone(),
+            reason,
+        };
+        if !std::path::Path::new(&args.f).is_file() {
+            return Err(load("no such file".to_string()));
+        }
+
+        // build env
         let env = Environment::builder()
             .with_name("YOLOv8")
-            .with_log_level(ort::LoggingLevel::Verbose)
-            .build()?
+            .with_log_level(args.log_level.into())
+            .build()
+            .map_err(|e| Error::Ep(e.to_string()))?
             .into_arc();
-        let session = SessionBuilder::new(&env)?.with_model_from_file(&args.f)?;
-
-        // get inputs
-        let mut inputs = OrtInputs::new(&session);
 
-        // batch size
-        let mut batch = args.batch;
-        let batch = if inputs.shapes[0][0] == -1 {
-            batch
-        } else {
-            assert_eq!(
-                inputs.shapes[0][0] as u32, batch.opt,
-                "Expected batch size: {}, got {}. Try using `--batch {}`.",
-                inputs.shapes[0][0] as u32, batch.opt, inputs.shapes[0][0] as u32
-            );
-            batch.opt = inputs.shapes[0][0] as u32;
-            batch
+        // build provider, TensorRT wants its shape profile before the session exists,
+        // so read the inputs straight from the file instead of building the session twice
+        let (ep, provider) = match args.ep {
+            OrtEP::Cuda(device_id) => Self::set_ep_cuda(device_id),
+            OrtEP::Trt(device_id) => {
+                let model = ModelInfo::read(&args.f).map_err(load)?;
+                let (inputs, batch, layout) =
+                    ModelInputs::from_model(&model).resolve(&args, |key| model.metadata(key))?;
+                Self::set_ep_trt(device_id, args.trt_fp16, &batch, &inputs, layout)?
+            }
+            OrtEP::OneDnn => Self::set_ep_onednn(args.memory_arena),
+            OrtEP::OpenVino => Self::set_ep_openvino(args.intra_threads),
+            OrtEP::Cpu => (OrtEP::Cpu, ExecutionProvider::CPU(Default::default())),
+        };
+        let cpu = ExecutionProvider::CPU(CPUExecutionProviderOptions {
+            use_arena: args.memory_arena,
+        });
+        let name = provider.as_str();
+        let providers = match provider {
+            ExecutionProvider::CPU(_) => vec![cpu],
+            provider => vec![provider, cpu],
         };
 
-        // input size: height and width
-        let height = if inputs.shapes[0][2] == -1 {
-            match args.image_size.0 {
-                Some(height) => height,
-                None => panic!("Failed to get model height. Make it explicit with `--height`"),
+        // build session with the provider and session options
+        let ep_err = |e: ort::OrtError| Error::Ep(e.to_string());
+        let mut builder = SessionBuilder::new(&env)
+            .map_err(ep_err)?
+            .with_optimization_level(args.opt_level.into())
+            .map_err(ep_err)?
+            .with_execution_providers(providers)
+            .map_err(ep_err)?;
+        if let Some(n) = args.intra_threads {
+            builder = builder.with_intra_threads(n).map_err(ep_err)?;
+        }
+        if let Some(n) = args.inter_threads {
+            // inter-op threads only run independent branches in parallel execution mode
+            builder = builder
+                .with_parallel_execution(true)
+                .and_then(|x| x.with_inter_threads(n))
+                .map_err(ep_err)?;
+        }
+        if !args.memory_arena {
+            builder = builder.with_memory_pattern(false).map_err(ep_err)?;
+        }
+        if let Some(path) = &args.profiling {
+            // JSON trace, only written out by `end_profiling`, dropping the session leaves it empty
+            builder = builder.with_profiling(path).map_err(ep_err)?;
+        }
+        // ort 1.16 doesn't return which provider it registered, it skips the ones that fail
+        // and only logs the one that worked. that log text is all there is to match, so if
+        // nothing matches (e.g. the wording changed) keep the requested EP as unconfirmed
+        // rather than claim a fallback that may not have happened
+        let registered = Registered::default();
+        let session = tracing::subscriber::with_default(registered.clone(), || {
+            builder.with_model_from_file(&args.f)
+        })
+        .map_err(|e| load(e.to_string()))?;
+        let (ep, ep_confirmed) = match registered.name() {
+            Some(x) if x == name => (ep, true),
+            Some(_) => {
+                println!("> {:?} failed to register! Using CPU.", ep);
+                (OrtEP::Cpu, true)
             }
-        } else {
-            inputs.shapes[0][2] as u32
-        };
-        let width = if inputs.shapes[0][3] == -1 {
-            match args.image_size.1 {
-                Some(width) => width,
-                None => panic!("Failed to get model width. Make it explicit with `--width`"),
+            None => {
+                let confirmed = ep == OrtEP::Cpu;
+                (ep, confirmed)
             }
-        } else {
-            inputs.shapes[0][3] as u32
         };
-        inputs.sizes.push(vec![height, width]);
 
-        // build provider
-        let (ep, provider) = match args.ep {
-            OrtEP::Cuda(device_id) => Self::set_ep_cuda(device_id),
-            OrtEP::Trt(device_id) => Self::set_ep_trt(device_id, args.trt_fp16, &batch, &inputs),
-            _ => (OrtEP::Cpu, ExecutionProvider::CPU(Default::default())),
-        };
+        // inputs, batch, layout and input size
+        let metadata = |key: &str| session.metadata().ok()?.custom(key).ok()?;
+        let (inputs, batch, layout) = session_inputs(&session).resolve(&args, metadata)?;
+        match inputs.dtypes[0] {
+            DType::Float32 | DType::Float16 | DType::Uint8 | DType::Int8 => {}
+            dtype => {
+                return Err(Error::Dtype(format!(
+                    "Unsupported input dtype: {:?}, expected float32, float16, uint8 or int8",
+                    dtype
+                )))
+            }
+        }
+
+        let n = session.outputs.len();
+        let dequant = output_quantization(n, metadata)?;
 
-        // build session again with the new provider
-        let session = SessionBuilder::new(&env)?
-            // .with_optimization_level(ort::GraphOptimizationLevel::Level3)?
-            .with_execution_providers([provider])?
-            .with_model_from_file(args.f)?;
+        let f16_inputs = vec![Array::from_elem(IxDyn(&[0]), f16::ZERO); inputs.names.len()];
 
         Ok(Self {
             session,
             ep,
+            ep_confirmed,
             batch,
             inputs,
+            layout,
+            dequant,
+            f16_inputs,
+            outputs: vec![Array::zeros(IxDyn(&[0])); n],
+            profiling: args.profiling.is_some(),
         })
     }
 
-    pub fn fetch_inputs_from_session(
-        session: &Session,
-    ) -> (Vec<Vec<i32>>, Vec<TensorElementDataType>, Vec<String>) {
-        // get inputs attrs from ONNX model
-        let mut shapes = Vec::new();
-        let mut dtypes = Vec::new();
-        let mut names = Vec::new();
-        for i in session.inputs.iter() {
-            let shape: Vec<i32> = i
-                .dimensions()
-                .map(|x| if let Some(x) = x { x as i32 } else { -1i32 })
-                .collect();
-            shapes.push(shape);
-            dtypes.push(i.input_type);
-            names.push(i.name.clone());
-        }
-        (shapes, dtypes, names)
-    }
-
     pub fn set_ep_cuda(device_id: u32) -> (OrtEP, ExecutionProvider) {
         // set CUDA
         if ExecutionProvider::CUDA(Default::default()).is_available() {
@@ -184,32 +322,70 @@ impl OrtBackend {
         }
     }
 
+    pub fn set_ep_onednn(use_arena: bool) -> (OrtEP, ExecutionProvider) {
+        // set oneDNN (DNNL), CPU only
+        if ExecutionProvider::OneDNN(Default::default()).is_available() {
+            (
+                OrtEP::OneDnn,
+                ExecutionProvider::OneDNN(OneDNNExecutionProviderOptions { use_arena }),
+            )
+        } else {
+            println!("> oneDNN is not available! Using CPU.");
+            (OrtEP::Cpu, ExecutionProvider::CPU(Default::default()))
+        }
+    }
+
+    pub fn set_ep_openvino(threads: Option<i16>) -> (OrtEP, ExecutionProvider) {
+        // set OpenVINO on the CPU device
+        if ExecutionProvider::OpenVINO(Default::default()).is_available() {
+            let mut options = OpenVINOExecutionProviderOptions {
+                device_type: Some("CPU_FP32".to_string()),
+                ..Default::default()
+            };
+            if let Some(n) = threads {
+                options.num_threads = n.max(1) as usize;
+            }
+            (OrtEP::OpenVino, ExecutionProvider::OpenVINO(options))
+        } else {
+            println!("> OpenVINO is not available! Using CPU.");
+            (OrtEP::Cpu, ExecutionProvider::CPU(Default::default()))
+        }
+    }
+
     pub fn set_ep_trt(
         device_id: u32,
         fp16: bool,
         batch: &Batch,
-        inputs: &OrtInputs,
-    ) -> (OrtEP, ExecutionProvider) {
+        inputs: &ModelInputs,
+        layout: Layout,
+    ) -> Result<(OrtEP, ExecutionProvider), Error> {
         // set TensorRT
         if ExecutionProvider::TensorRT(Default::default()).is_available() {
             let (height, width) = (inputs.sizes[0][0], inputs.sizes[0][1]);
 
             // dtype match checking
-            if inputs.dtypes[0] == TensorElementDataType::Float16 && !fp16 {
-                panic!(
+            if inputs.dtypes[0] == DType::Float16 && !fp16 {
+                return Err(Error::Dtype(format!(
                     "Dtype mismatch! Expected: Float32, got: {:?}. You should use `--fp16`",
                     inputs.dtypes[0]
-                );
+                )));
             }
 
             // dynamic shape: input_tensor_1:dim_1xdim_2x...,input_tensor_2:dim_3xdim_4x...,...
             let mut opt_string = String::new();
             let mut min_string = String::new();
             let mut max_string = String::new();
-            for name in inputs.names.iter() {
-                let s_opt = format!("{}:{}x3x{}x{},", name, batch.opt, height, width);
-                let s_min = format!("{}:{}x3x{}x{},", name, batch.min, height, width);
-                let s_max = format!("{}:{}x3x{}x{},", name, batch.max, height, width);
+            let dims = |batch: u32| {
+                layout
+                    .shape(batch as usize, height as usize, width as usize)
+                    .map(|x| x.to_string())
+                    .join("x")
+            };
+            // the image input, other inputs (e.g. recurrent states) keep their own shapes
+            for name in inputs.names.iter().take(1) {
+                let s_opt = format!("{}:{},", name, dims(batch.opt));
+                let s_min = format!("{}:{},", name, dims(batch.min));
+                let s_max = format!("{}:{},", name, dims(batch.max));
                 opt_string.push_str(s_opt.as_str());
                 min_string.push_str(s_min.as_str());
                 max_string.push_str(s_max.as_str());
@@ -217,7 +393,7 @@ impl OrtBackend {
             let _ = opt_string.pop();
             let _ = min_string.pop();
             let _ = max_string.pop();
-            (
+            Ok((
                 OrtEP::Trt(device_id),
                 ExecutionProvider::TensorRT(TensorRTExecutionProviderOptions {
                     device_id,
@@ -228,10 +404,10 @@ impl OrtBackend {
                     profile_opt_shapes: opt_string,
                     ..Default::default()
                 }),
-            )
+            ))
         } else {
             println!("> TensorRT is not available! Try using CUDA...");
-            Self::set_ep_cuda(device_id)
+            Ok(Self::set_ep_cuda(device_id))
         }
     }
 
@@ -239,103 +415,195 @@ impl OrtBackend {
         // fetch value from onnx model file by key
         match self.session.metadata() {
             Err(_) => None,
-            Ok(metadata) => match metadata.custom(key) {
-                Err(_) => None,
-                Ok(value) => value,
-            },
+            Ok(metadata) => metadata.custom(key).unwrap_or_default(),
         }
     }
 
-    pub fn run(
-        &self,
-        input_tensor: Array<f32, IxDyn>,
-        profile: bool,
-    ) -> Result<Vec<Array<f32, IxDyn>>> {
-        // ORT inference
-        match self.dtype() {
-            TensorElementDataType::Float16 => self.run_fp16(input_tensor, profile),
-            TensorElementDataType::Float32 => self.run_fp32(input_tensor, profile),
-            _ => todo!(),
-        }
+    pub fn run(&mut self, input_tensor: TensorView, profile: bool) -> Result<()> {
+        // ORT inference, single input models, results in `outputs()`
+        self.run_multi(&[input_tensor], profile)
     }
 
-    pub fn run_fp16(
-        &self,
-        input_tensor: Array<f32, IxDyn>,
-        profile: bool,
-    ) -> Result<Vec<Array<f32, IxDyn>>> {
-        // f32->f16
+    pub fn run_multi(&mut self, xs: &[TensorView], profile: bool) -> Result<()> {
+        // ORT inference, one tensor per model input in session order
+        // inputs are wrapped, not copied, and the f16 staging and f32 output buffers are only
+        // reallocated on shape changes. each run still builds its input `Value`s and ORT hands
+        // back fresh output tensors (1.16's IoBinding can't bind outputs to our buffers), which
+        // come from the memory arena and are copied into ours, so memory stays flat over time
+        if xs.len() != self.inputs.names.len() {
+            bail!(
+                "Expected {} inputs {:?}, got {}",
+                self.inputs.names.len(),
+                self.inputs.names,
+                xs.len()
+            );
+        }
+
+        // inputs are written in the model dtype by preprocessing, only f32 tensors fed to f16
+        // inputs (e.g. recurrent states) are converted, into a reused buffer
         let t = std::time::Instant::now();
-        let xs = input_tensor.mapv(f16::from_f32);
-        if profile {
+        let mut converted = false;
+        for ((x, dtype), buf) in xs.iter().zip(&self.inputs.dtypes).zip(&mut self.f16_inputs) {
+            if let (TensorView::F32(x), DType::Float16) = (x, dtype) {
+                if buf.shape() != x.shape() {
+                    *buf = Array::from_elem(x.raw_dim(), f16::ZERO);
+                }
+                buf.zip_mut_with(x, |y, &x| *y = f16::from_f32(x));
+                converted = true;
+            }
+        }
+        if profile && converted {
             println!("[ORT f32->f16]: {:?}", t.elapsed());
         }
 
-        // h2d
+        // h2d, no copies
         let t = std::time::Instant::now();
-        let xs = CowArray::from(xs);
-        let xs = vec![Value::from_array(self.session.allocator(), &xs)?];
+        let inputs = xs
+            .iter()
+            .zip(&self.inputs.dtypes)
+            .zip(&self.inputs.names)
+            .zip(&self.f16_inputs)
+            .map(|(((x, dtype), name), buf)| match (x, dtype) {
+                (TensorView::F32(x), DType::Float32) => Ok(Input::F32(CowArray::from(x.view()))),
+                (TensorView::F16(x), DType::Float16) => Ok(Input::F16(CowArray::from(x.view()))),
+                (TensorView::F32(_), DType::Float16) => Ok(Input::F16(CowArray::from(buf.view()))),
+                (TensorView::U8(x), DType::Uint8) => Ok(Input::U8(CowArray::from(x.view()))),
+                (TensorView::I8(x), DType::Int8) => Ok(Input::I8(CowArray::from(x.view()))),
+                (_, dtype) => bail!(
+                    "Input `{}` expects {:?}, got {:?} {:?}",
+                    name,
+                    dtype,
+                    x.dtype(),
+                    x.shape()
+                ),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let allocator = self.session.allocator();
+        let values = inputs
+            .iter()
+            .map(|x| match x {
+                Input::F32(x) => Value::from_array(allocator, x),
+                Input::F16(x) => Value::from_array(allocator, x),
+                Input::U8(x) => Value::from_array(allocator, x),
+                Input::I8(x) => Value::from_array(allocator, x),
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
         if profile {
             println!("[ORT H2D]: {:?}", t.elapsed());
         }
 
-        // run
+        // run, ORT serves its own outputs from the memory arena
         let t = std::time::Instant::now();
-        let ys = self.session.run(xs)?;
+        let ys = self.session.run(values)?;
         if profile {
             println!("[ORT Inference]: {:?}", t.elapsed());
         }
 
-        // d2h
-        Ok(ys
+        // d2h, one pass per output: f32 is copied, f16 widened and integers dequantised on the way
+        let t = std::time::Instant::now();
+        for (((y, o), dst), q) in ys
             .iter()
-            .map(|x| {
-                // d2h
-                let t = std::time::Instant::now();
-                let x = x.try_extract::<_>().unwrap().view().clone().into_owned();
-                if profile {
-                    println!("[ORT D2H]: {:?}", t.elapsed());
+            .zip(&self.session.outputs)
+            .zip(&mut self.outputs)
+            .zip(&self.dequant)
+        {
+            let q = |x: f32| q.dequantize(x);
+            match o.output_type {
+                TensorElementDataType::Float32 => {
+                    copy_into(dst, &y.try_extract::<f32>()?.view(), |x| x)
                 }
-
-                // f16->f32
-                let t_ = std::time::Instant::now();
-                let x = x.mapv(f16::to_f32);
-                if profile {
-                    println!("[ORT f16->f32]: {:?}", t_.elapsed());
+                TensorElementDataType::Float16 => {
+                    copy_into(dst, &y.try_extract::<f16>()?.view(), f16::to_f32)
                 }
-                x
-            })
-            .collect::<Vec<Array<_, _>>>())
-    }
-
-    pub fn run_fp32(&self, xs: Array<f32, IxDyn>, profile: bool) -> Result<Vec<Array<f32, IxDyn>>> {
-        // h2d
-        let t = std::time::Instant::now();
-        let xs = CowArray::from(xs);
-        let xs = vec![Value::from_array(self.session.allocator(), &xs)?];
+                TensorElementDataType::Float64 => {
+                    copy_into(dst, &y.try_extract::<f64>()?.view(), |x| x as f32)
+                }
+                TensorElementDataType::Uint8 => {
+                    copy_into(dst, &y.try_extract::<u8>()?.view(), |x| q(x as f32))
+                }
+                TensorElementDataType::Int8 => {
+                    copy_into(dst, &y.try_extract::<i8>()?.view(), |x| q(x as f32))
+                }
+                TensorElementDataType::Uint16 => {
+                    copy_into(dst, &y.try_extract::<u16>()?.view(), |x| q(x as f32))
+                }
+                TensorElementDataType::Int16 => {
+                    copy_into(dst, &y.try_extract::<i16>()?.view(), |x| q(x as f32))
+                }
+                TensorElementDataType::Int32 => {
+                    copy_into(dst, &y.try_extract::<i32>()?.view(), |x| q(x as f32))
+                }
+                TensorElementDataType::Int64 => {
+                    copy_into(dst, &y.try_extract::<i64>()?.view(), |x| q(x as f32))
+                }
+                dtype => bail!("Unsupported output dtype: {:?}", dtype),
+            }
+        }
         if profile {
-            println!("[ORT H2D]: {:?}", t.elapsed());
+            println!("[ORT D2H]: {:?}", t.elapsed());
         }
+        Ok(())
+    }
 
-        // run
-        let t = std::time::Instant::now();
-        let ys = self.session.run(xs)?;
-        if profile {
-            println!("[ORT Inference]: {:?}", t.elapsed());
+    pub fn outputs(&self) -> &[Array<f32, IxDyn>] {
+        // results of the last run, as f32
+        &self.outputs
+    }
+
+    pub fn recurrent(&self, links: &[(&str, &str)]) -> Result<Recurrent> {
+        // (output name, input name) pairs, the output is fed back as the input on the next run
+        let output_names = self.output_names();
+        let mut recurrent = Recurrent::default();
+        for &(output, input) in links {
+            let Some(o) = output_names.iter().position(|x| x == output) else {
+                bail!("Missing output `{}`, got {:?}", output, output_names);
+            };
+            let Some(i) = self.inputs.names.iter().position(|x| x == input) else {
+                bail!("Missing input `{}`, got {:?}", input, self.inputs.names);
+            };
+            // dynamic dims start at 1, e.g. RVM's `r1i` is a [1, 1, 1, 1] zero tensor
+            let shape: Vec<usize> = self.inputs.shapes[i]
+                .iter()
+                .map(|&x| if x == -1 { 1 } else { x as usize })
+                .collect();
+            recurrent.links.push((o, i));
+            recurrent.states.push(Array::zeros(IxDyn(&shape)));
+            recurrent.shapes.push(shape);
         }
+        Ok(recurrent)
+    }
 
-        // d2h
-        Ok(ys
-            .iter()
-            .map(|x| {
-                let t = std::time::Instant::now();
-                let x = x.try_extract::<_>().unwrap().view().clone().into_owned();
-                if profile {
-                    println!("[ORT D2H]: {:?}", t.elapsed());
+    pub fn run_recurrent(
+        &mut self,
+        xs: &[(&str, TensorView)],
+        state: &mut Recurrent,
+        profile: bool,
+    ) -> Result<()> {
+        // named inputs, everything else comes from the recurrent state
+        {
+            let mut inputs = Vec::with_capacity(self.inputs.names.len());
+            for (i, name) in self.inputs.names.iter().enumerate() {
+                if let Some((_, x)) = xs.iter().find(|(n, _)| n == name) {
+                    inputs.push(x.view());
+                } else if let Some(k) = state.links.iter().position(|&(_, x)| x == i) {
+                    inputs.push(state.states[k].view().into());
+                } else {
+                    bail!("Missing input `{}`", name);
                 }
-                x
-            })
-            .collect::<Vec<Array<_, _>>>())
+            }
+            self.run_multi(&inputs, profile)?;
+        }
+
+        // feed back, in place unless the shape changed
+        for (k, &(o, _)) in state.links.iter().enumerate() {
+            let (x, y) = (&mut state.states[k], &self.outputs[o]);
+            if x.shape() == y.shape() {
+                x.assign(y);
+            } else {
+                *x = y.clone();
+            }
+        }
+        Ok(())
     }
 
     pub fn output_shapes(&self) -> Vec<Vec<i32>> {
@@ -350,6 +618,22 @@ impl OrtBackend {
         shapes
     }
 
+    pub fn end_profiling(&self) -> Result<Option<String>> {
+        // write the ORT profile, returns its path, `None` without `--ort-profile`
+        if !self.profiling {
+            return Ok(None);
+        }
+        Ok(Some(self.session.end_profiling()?))
+    }
+
+    pub fn output_names(&self) -> Vec<String> {
+        self.session
+            .outputs
+            .iter()
+            .map(|x| x.name.clone())
+            .collect()
+    }
+
     pub fn output_dtypes(&self) -> Vec<TensorElementDataType> {
         let mut dtypes = Vec::new();
         self.session
@@ -367,11 +651,11 @@ impl OrtBackend {
         &self.inputs.names
     }
 
-    pub fn input_dtypes(&self) -> &Vec<TensorElementDataType> {
+    pub fn input_dtypes(&self) -> &Vec<DType> {
         &self.inputs.dtypes
     }
 
-    pub fn dtype(&self) -> TensorElementDataType {
+    pub fn dtype(&self) -> DType {
         self.input_dtypes()[0]
     }
 
@@ -384,11 +668,15 @@ impl OrtBackend {
     }
 
     pub fn is_height_dynamic(&self) -> bool {
-        self.input_shapes()[0][2] == -1
+        self.input_shapes()[0][self.layout.hw_axes().0] == -1
     }
 
     pub fn is_width_dynamic(&self) -> bool {
-        self.input_shapes()[0][3] == -1
+        self.input_shapes()[0][self.layout.hw_axes().1] == -1
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
     }
 
     pub fn batch(&self) -> u32 {
@@ -403,67 +691,85 @@ impl OrtBackend {
         &self.ep
     }
 
-    pub fn names(&self) -> Option<Vec<String>> {
-        // class names, metadata parsing
-        // String format: `{0: 'person', 1: 'bicycle', 2: 'sports ball', ..., 27: "yellow_lady's_slipper"}`
-        match self.fetch_from_metadata("names") {
-            Some(names) => {
-                let re = Regex::new(r#"(['"])([-()\w '"]+)(['"])"#).unwrap();
-                let mut names_ = vec![];
-                for (_, [_, name, _]) in re.captures_iter(&names).map(|x| x.extract()) {
-                    names_.push(name.to_string());
-                }
-                Some(names_)
-            }
-            None => None,
-        }
+    pub fn ep_confirmed(&self) -> bool {
+        // false when ORT's log didn't say which provider it registered
+        self.ep_confirmed
     }
 
-    pub fn nk(&self) -> Option<u32> {
+    pub fn nk(&self) -> Result<Option<u32>, Error> {
         // num_keypoints, metadata parsing: String `nk` in onnx model: `[17, 3]`
-        match self.fetch_from_metadata("kpt_shape") {
-            None => None,
-            Some(kpt_string) => {
-                let re = Regex::new(r"([0-9]+), ([0-9]+)").unwrap();
-                let caps = re.captures(&kpt_string).unwrap();
-                Some(caps.get(1).unwrap().as_str().parse::<u32>().unwrap())
-            }
-        }
+        let Some(kpt_string) = self.fetch_from_metadata("kpt_shape") else {
+            return Ok(None);
+        };
+        let re = Regex::new(r"([0-9]+), ([0-9]+)").unwrap();
+        re.captures(&kpt_string)
+            .and_then(|caps| caps[1].parse::<u32>().ok())
+            .map(Some)
+            .ok_or_else(|| Error::Metadata {
+                key: "kpt_shape".to_string(),
+                reason: format!("expected `[nk, dims]`, got `{}`", kpt_string),
+            })
     }
+}
 
-    pub fn nc(&self) -> Option<u32> {
-        // num_classes
-        match self.names() {
-            // by names
-            Some(names) => Some(names.len() as u32),
-            None => {
-                if self.output_shapes()[0][1] == -1 {
-                    None
-                } else {
-                    // cxywhclssnm
-                    Some((self.output_shapes()[0][1] - self.output_shapes()[1][1]) as u32 - 4)
-                }
-            }
-        }
+impl InferenceBackend for OrtBackend {
+    fn dtype(&self) -> DType {
+        OrtBackend::dtype(self)
     }
 
-    pub fn nm(&self) -> u32 {
-        self.output_shapes()[1][1] as u32
+    fn layout(&self) -> Layout {
+        OrtBackend::layout(self)
     }
 
-    pub fn na(&self) -> Option<u32> {
-        if self.output_shapes()[0][2] == -1 {
-            None
-        } else {
-            Some(self.output_shapes()[0][2] as u32)
-        }
+    fn batch(&self) -> u32 {
+        OrtBackend::batch(self)
+    }
+
+    fn height(&self) -> u32 {
+        OrtBackend::height(self)
+    }
+
+    fn width(&self) -> u32 {
+        OrtBackend::width(self)
+    }
+
+    fn is_batch_dynamic(&self) -> bool {
+        OrtBackend::is_batch_dynamic(self)
+    }
+
+    fn is_height_dynamic(&self) -> bool {
+        OrtBackend::is_height_dynamic(self)
+    }
+
+    fn is_width_dynamic(&self) -> bool {
+        OrtBackend::is_width_dynamic(self)
+    }
+
+    fn output_shapes(&self) -> Vec<Vec<i32>> {
+        OrtBackend::output_shapes(self)
+    }
+
+    fn fetch_from_metadata(&self, key: &str) -> Option<String> {
+        OrtBackend::fetch_from_metadata(self, key)
+    }
+
+    fn ep(&self) -> &OrtEP {
+        OrtBackend::ep(self)
+    }
+
+    fn ep_confirmed(&self) -> bool {
+        OrtBackend::ep_confirmed(self)
+    }
+
+    fn run(&mut self, xs: TensorView, profile: bool) -> Result<()> {
+        OrtBackend::run(self, xs, profile)
     }
 
-    pub fn author(&self) -> Option<String> {
-        self.fetch_from_metadata("author")
+    fn outputs(&self) -> &[Array<f32, IxDyn>] {
+        OrtBackend::outputs(self)
     }
 
-    pub fn version(&self) -> Option<String> {
-        self.fetch_from_metadata("version")
+    fn end_profiling(&self) -> Result<Option<String>> {
+        OrtBackend::end_profiling(self)
     }
 }
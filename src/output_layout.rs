@@ -0,0 +1,96 @@
+use clap::ValueEnum;
+use ndarray::{s, ArrayView1, ArrayView2};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputLayout {
+    // detection head layouts
+    // v8: YOLOv8/v9/YOLO11, [bs, 4 + nc + nm, anchors], cxcywh
+    // v5: YOLOv5-seg, [bs, anchors, 5 + nc + nm], cxcywh + objectness
+    // e2e: YOLOv10 end-to-end, [bs, N, 6 + nm], xyxy + score + class, already NMS'd
+    V8,
+    V5,
+    E2e,
+}
+
+impl OutputLayout {
+    pub fn detect(shape: &[i32], nm: u32, nc: Option<u32>, description: Option<&str>) -> Self {
+        // metadata first, e.g. `Ultralytics YOLOv10n model trained on coco.yaml`
+        if let Some(description) = description {
+            if description.contains("YOLOv10") {
+                return OutputLayout::E2e;
+            }
+            if description.contains("YOLOv5") {
+                return OutputLayout::V5;
+            }
+        }
+
+        let nm = nm as i32;
+        let &[_, a, b] = shape else {
+            return OutputLayout::V8;
+        };
+        match nc.map(|nc| nc as i32) {
+            Some(nc) if a == 4 + nc + nm => OutputLayout::V8,
+            Some(nc) if b == 5 + nc + nm && a > 1000 => OutputLayout::V5,
+            _ if b == 6 + nm && (0..=1000).contains(&a) => OutputLayout::E2e,
+            // anchors are the long axis
+            _ if a > 0 && b > 0 && a > b => OutputLayout::V5,
+            _ => OutputLayout::V8,
+        }
+    }
+
+    pub fn nc(&self, shape: &[i32], nm: u32) -> Option<u32> {
+        // num_classes from the head width, unknown for end-to-end heads
+        let nm = nm as i32;
+        let nc = match (self, shape) {
+            (OutputLayout::V8, &[_, a, _]) if a != -1 => a - 4 - nm,
+            (OutputLayout::V5, &[_, _, b]) if b != -1 => b - 5 - nm,
+            _ => return None,
+        };
+        (nc > 0).then_some(nc as u32)
+    }
+
+    pub fn is_end_to_end(&self) -> bool {
+        *self == OutputLayout::E2e
+    }
+
+    pub fn anchors<'a>(&self, preds: ArrayView2<'a, f32>) -> ArrayView2<'a, f32> {
+        // one row per anchor
+        match self {
+            OutputLayout::V8 => preds.reversed_axes(),
+            OutputLayout::V5 | OutputLayout::E2e => preds,
+        }
+    }
+
+    pub fn decode<'a>(
+        &self,
+        pred: ArrayView1<'a, f32>,
+        nc: usize,
+        nm: usize,
+    ) -> ([f32; 4], usize, f32, ArrayView1<'a, f32>) {
+        // anchor row -> (cxcywh, class id, confidence, mask coefs)
+        let best = |clss: ArrayView1<f32>| {
+            clss.iter()
+                .copied()
+                .enumerate()
+                .reduce(|max, x| if x.1 > max.1 { x } else { max })
+                .unwrap_or((0, 0.0))
+        };
+        let coefs = pred.slice_move(s![pred.len() - nm..]);
+        match self {
+            OutputLayout::V8 => {
+                let (id, confidence) = best(pred.slice(s![4..4 + nc]));
+                ([pred[0], pred[1], pred[2], pred[3]], id, confidence, coefs)
+            }
+            OutputLayout::V5 => {
+                let (id, confidence) = best(pred.slice(s![5..5 + nc]));
+                let confidence = confidence * pred[4];
+                ([pred[0], pred[1], pred[2], pred[3]], id, confidence, coefs)
+            }
+            OutputLayout::E2e => {
+                let (x1, y1, x2, y2) = (pred[0], pred[1], pred[2], pred[3]);
+                let cxcywh = [(x1 + x2) / 2., (y1 + y2) / 2., x2 - x1, y2 - y1];
+                (cxcywh, pred[5].max(0.0) as usize, pred[4], coefs)
+            }
+        }
+    }
+}
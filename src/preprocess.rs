@@ -0,0 +1,640 @@
+use clap::ValueEnum;
+use half::f16;
+use image::{DynamicImage, GenericImageView, Luma};
+use ndarray::ArrayView2;
+
+use crate::{Args, Mask};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ResizeFilter {
+    // interpolation used when letterboxing frames into the model input
+    #[default]
+    Bilinear,
+    Area,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ChannelOrder {
+    // channel order expected by the model
+    #[default]
+    Rgb,
+    Bgr,
+}
+
+impl ChannelOrder {
+    pub fn indices(&self) -> [usize; 3] {
+        // source channel feeding each model channel
+        match self {
+            ChannelOrder::Rgb => [0, 1, 2],
+            ChannelOrder::Bgr => [2, 1, 0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum PixelRange {
+    // value range of the pixels before mean/std
+    #[default]
+    #[value(name = "0-1")]
+    Unit,
+    #[value(name = "0-255")]
+    Byte,
+}
+
+impl PixelRange {
+    pub fn scale(&self) -> f32 {
+        match self {
+            PixelRange::Unit => 1.0 / 255.0,
+            PixelRange::Byte => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Layout {
+    // memory layout of the model input tensor
+    #[default]
+    Nchw,
+    Nhwc,
+}
+
+impl Layout {
+    pub fn detect(shape: &[i32]) -> Option<Self> {
+        // guess from where the 3 colour channels sit
+        match shape {
+            [_, 3, _, _] => Some(Layout::Nchw),
+            [_, _, _, 3] => Some(Layout::Nhwc),
+            _ => None,
+        }
+    }
+
+    pub fn hw_axes(&self) -> (usize, usize) {
+        match self {
+            Layout::Nchw => (2, 3),
+            Layout::Nhwc => (1, 2),
+        }
+    }
+
+    pub fn shape(&self, batch: usize, height: usize, width: usize) -> [usize; 4] {
+        match self {
+            Layout::Nchw => [batch, 3, height, width],
+            Layout::Nhwc => [batch, height, width, 3],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantization {
+    // affine integer quantisation, q = round(x / scale) + zero_point
+    pub scale: f32,
+    pub zero_point: i32,
+}
+
+impl Default for Quantization {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            zero_point: 0,
+        }
+    }
+}
+
+impl Quantization {
+    pub fn quantize(&self, x: f32) -> f32 {
+        (x / self.scale).round() + self.zero_point as f32
+    }
+
+    pub fn dequantize(&self, q: f32) -> f32 {
+        (q - self.zero_point as f32) * self.scale
+    }
+}
+
+pub trait InputElement: Copy + Default + Send + Sync + 'static {
+    // normalised pixel value -> model input element
+    fn from_normalised(x: f32, spec: &InputSpec) -> Self;
+}
+
+impl InputElement for f32 {
+    fn from_normalised(x: f32, _: &InputSpec) -> Self {
+        x
+    }
+}
+
+impl InputElement for f16 {
+    fn from_normalised(x: f32, _: &InputSpec) -> Self {
+        f16::from_f32(x)
+    }
+}
+
+impl InputElement for u8 {
+    fn from_normalised(x: f32, spec: &InputSpec) -> Self {
+        spec.quantization(0).quantize(x).clamp(0.0, 255.0) as u8
+    }
+}
+
+impl InputElement for i8 {
+    fn from_normalised(x: f32, spec: &InputSpec) -> Self {
+        spec.quantization(-128).quantize(x).clamp(-128.0, 127.0) as i8
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputSpec {
+    // how pixels are ordered, scaled and normalised for the model input
+    pub order: ChannelOrder,
+    pub range: PixelRange,
+    pub mean: [f32; 3],
+    pub std: [f32; 3],
+    pub layout: Layout,
+    pub quant: Option<Quantization>,
+}
+
+impl Default for InputSpec {
+    fn default() -> Self {
+        Self {
+            order: ChannelOrder::Rgb,
+            range: PixelRange::Unit,
+            mean: [0.0; 3],
+            std: [1.0; 3],
+            layout: Layout::Nchw,
+            quant: None,
+        }
+    }
+}
+
+impl InputSpec {
+    pub fn from_metadata(fetch: impl Fn(&str) -> Option<String>) -> Self {
+        // optional keys: `channel_order`, `pixel_range`, `mean`, `std`, `layout`,
+        // `input_scale`, `input_zero_point`
+        // e.g. `channel_order: bgr`, `pixel_range: 0-255`, `mean: [103.5, 116.3, 123.7]`
+        let mut spec = Self::default();
+        let word = |key| fetch(key).map(|x| x.trim().trim_matches(['\'', '"']).to_string());
+        if let Some(order) =
+            word("channel_order").and_then(|x| ChannelOrder::from_str(&x, true).ok())
+        {
+            spec.order = order;
+        }
+        if let Some(range) = word("pixel_range").and_then(|x| PixelRange::from_str(&x, true).ok()) {
+            spec.range = range;
+        }
+        if let Some(layout) = word("layout").and_then(|x| Layout::from_str(&x, true).ok()) {
+            spec.layout = layout;
+        }
+        if let Some(mean) = fetch("mean").as_deref().and_then(parse_triple) {
+            spec.mean = mean;
+        }
+        if let Some(std) = fetch("std").as_deref().and_then(parse_triple) {
+            spec.std = std;
+        }
+        let scale = word("input_scale").and_then(|x| x.parse::<f32>().ok());
+        let zero_point = word("input_zero_point").and_then(|x| x.parse::<i32>().ok());
+        spec.quant = spec.merge_quant(scale, zero_point);
+        spec
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_args(mut self, config: &Args) -> anyhow::Result<Self> {
+        // cli overrides
+        if let Some(order) = config.channel_order {
+            self.order = order;
+        }
+        if let Some(range) = config.pixel_range {
+            self.range = range;
+        }
+        if let Some(mean) = &config.mean {
+            self.mean = mean[..]
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("`--mean` expects 3 values, got {:?}", mean))?;
+        }
+        if let Some(std) = &config.std {
+            self.std = std[..]
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("`--std` expects 3 values, got {:?}", std))?;
+        }
+        self.quant = self.merge_quant(config.input_scale, config.input_zero_point);
+        Ok(self)
+    }
+
+    fn merge_quant(&self, scale: Option<f32>, zero_point: Option<i32>) -> Option<Quantization> {
+        if scale.is_none() && zero_point.is_none() {
+            return self.quant;
+        }
+        let quant = self.quant.unwrap_or(Quantization {
+            scale: self.range.scale(),
+            zero_point: 0,
+        });
+        Some(Quantization {
+            scale: scale.unwrap_or(quant.scale),
+            zero_point: zero_point.unwrap_or(quant.zero_point),
+        })
+    }
+
+    pub fn quantization(&self, zero_point: i32) -> Quantization {
+        // integer inputs without explicit parameters take raw pixels, shifted by `zero_point`
+        self.quant.unwrap_or(Quantization {
+            scale: self.range.scale(),
+            zero_point,
+        })
+    }
+
+    pub fn lut<T: InputElement>(&self) -> [[T; 256]; 3] {
+        // u8 -> normalised (and quantised) value, per model channel
+        let mut lut = [[T::default(); 256]; 3];
+        for (c, table) in lut.iter_mut().enumerate() {
+            for (v, x) in table.iter_mut().enumerate() {
+                *x = T::from_normalised(
+                    (v as f32 * self.range.scale() - self.mean[c]) / self.std[c],
+                    self,
+                );
+            }
+        }
+        lut
+    }
+
+    pub fn pad_values<T: InputElement>(&self, color: [u8; 3]) -> [T; 3] {
+        let lut = self.lut::<T>();
+        let idx = self.order.indices();
+        [
+            lut[0][color[idx[0]] as usize],
+            lut[1][color[idx[1]] as usize],
+            lut[2][color[idx[2]] as usize],
+        ]
+    }
+}
+
+pub(crate) fn parse_list(s: &str) -> Option<Vec<f32>> {
+    // `[0.485, 0.456, 0.406]` or `0.485,0.456,0.406`
+    s.trim()
+        .trim_start_matches(['[', '('])
+        .trim_end_matches([']', ')'])
+        .split(',')
+        .map(|x| x.trim().parse::<f32>().ok())
+        .collect()
+}
+
+fn parse_triple(s: &str) -> Option<[f32; 3]> {
+    parse_list(s)?.try_into().ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Letterbox {
+    // mapping between an original frame and the padded model input
+    pub ratio: f32,
+    pub pad_x: u32,
+    pub pad_y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Letterbox {
+    pub fn new((w0, h0): (u32, u32), (w1, h1): (u32, u32), center: bool) -> Self {
+        let ratio = (w1 as f32 / w0 as f32).min(h1 as f32 / h0 as f32);
+        let width = ((w0 as f32 * ratio).round() as u32).clamp(1, w1);
+        let height = ((h0 as f32 * ratio).round() as u32).clamp(1, h1);
+        let (pad_x, pad_y) = if center {
+            // same rounding as Ultralytics' `LetterBox`
+            let dw = (w1 - width) as f32 / 2.0;
+            let dh = (h1 - height) as f32 / 2.0;
+            ((dw - 0.1).round() as u32, (dh - 0.1).round() as u32)
+        } else {
+            (0, 0)
+        };
+        Self {
+            ratio,
+            pad_x,
+            pad_y,
+            width,
+            height,
+        }
+    }
+
+    pub fn to_original(&self, x: f32, y: f32) -> (f32, f32) {
+        // model input coordinates -> original frame coordinates
+        (
+            (x - self.pad_x as f32) / self.ratio,
+            (y - self.pad_y as f32) / self.ratio,
+        )
+    }
+
+    pub fn to_input(&self, x: f32, y: f32) -> (f32, f32) {
+        // original frame coordinates -> model input coordinates
+        (
+            x * self.ratio + self.pad_x as f32,
+            y * self.ratio + self.pad_y as f32,
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct Kernel {
+    // per output index: (first source index, offset into `weights`, number of taps)
+    taps: Vec<(usize, usize, usize)>,
+    weights: Vec<f32>,
+}
+
+impl Kernel {
+    fn build(&mut self, src: usize, dst: usize, filter: ResizeFilter) {
+        self.taps.clear();
+        self.weights.clear();
+        let scale = src as f32 / dst as f32;
+        for i in 0..dst {
+            let offset = self.weights.len();
+            if filter == ResizeFilter::Area && scale > 1.0 {
+                // box filter, edge pixels weighted by coverage
+                let lo = i as f32 * scale;
+                let hi = lo + scale;
+                let first = lo.floor() as usize;
+                let last = (hi.ceil() as usize).min(src);
+                for j in first..last {
+                    let w = (hi.min(j as f32 + 1.0) - lo.max(j as f32)) / scale;
+                    self.weights.push(w);
+                }
+                self.taps.push((first, offset, last - first));
+            } else {
+                // bilinear with aligned pixel centres, area falls back to it when upscaling
+                let x = ((i as f32 + 0.5) * scale - 0.5).clamp(0.0, (src - 1) as f32);
+                let x0 = x.floor() as usize;
+                let x1 = (x0 + 1).min(src - 1);
+                let t = x - x0 as f32;
+                if x1 == x0 || t == 0.0 {
+                    self.weights.push(1.0);
+                    self.taps.push((x0, offset, 1));
+                } else {
+                    self.weights.extend([1.0 - t, t]);
+                    self.taps.push((x0, offset, 2));
+                }
+            }
+        }
+    }
+
+    fn get(&self, i: usize) -> (usize, &[f32]) {
+        let (first, offset, n) = self.taps[i];
+        (first, &self.weights[offset..offset + n])
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Resizer {
+    // separable resize of packed 8-bit pixels, buffers are reused between calls
+    filter: ResizeFilter,
+    key: Option<(usize, usize, usize, usize)>,
+    kx: Kernel,
+    ky: Kernel,
+    tmp: Vec<f32>,
+    row: Vec<f32>,
+    dst: Vec<u8>,
+}
+
+impl Resizer {
+    pub fn new(filter: ResizeFilter) -> Self {
+        Self {
+            filter,
+            ..Default::default()
+        }
+    }
+
+    pub fn filter(&self) -> ResizeFilter {
+        self.filter
+    }
+
+    pub fn resize(
+        &mut self,
+        src: &[u8],
+        (sw, sh): (usize, usize),
+        channels: usize,
+        (dw, dh): (usize, usize),
+    ) -> &[u8] {
+        assert_eq!(src.len(), sw * sh * channels, "packed source size mismatch");
+
+        // weight tables only depend on the sizes
+        if self.key != Some((sw, sh, dw, dh)) {
+            self.kx.build(sw, dw, self.filter);
+            self.ky.build(sh, dh, self.filter);
+            self.key = Some((sw, sh, dw, dh));
+        }
+
+        // horizontal pass: (sh, sw, c) u8 -> (sh, dw, c) f32
+        let row_len = dw * channels;
+        self.tmp.resize(sh * row_len, 0.0);
+        match channels {
+            3 => horizontal::<3>(src, sw, &self.kx, &mut self.tmp, row_len),
+            4 => horizontal::<4>(src, sw, &self.kx, &mut self.tmp, row_len),
+            _ => panic!("unsupported channel count: {}", channels),
+        }
+
+        // vertical pass: whole rows at a time, (sh, dw, c) f32 -> (dh, dw, c) u8
+        self.row.resize(row_len, 0.0);
+        self.dst.resize(dh * row_len, 0);
+        for (y, dst_row) in self.dst.chunks_exact_mut(row_len).enumerate() {
+            let (first, weights) = self.ky.get(y);
+            self.row.fill(0.0);
+            for (k, &w) in weights.iter().enumerate() {
+                let tmp_row = &self.tmp[(first + k) * row_len..(first + k + 1) * row_len];
+                for (o, &t) in self.row.iter_mut().zip(tmp_row) {
+                    *o += w * t;
+                }
+            }
+            for (d, &v) in dst_row.iter_mut().zip(&self.row) {
+                *d = (v + 0.5).clamp(0.0, 255.0) as u8;
+            }
+        }
+
+        &self.dst
+    }
+}
+
+fn horizontal<const C: usize>(src: &[u8], sw: usize, kx: &Kernel, tmp: &mut [f32], row_len: usize) {
+    for (src_row, tmp_row) in src.chunks_exact(sw * C).zip(tmp.chunks_exact_mut(row_len)) {
+        for (x, out) in tmp_row.chunks_exact_mut(C).enumerate() {
+            let (first, weights) = kx.get(x);
+            let mut acc = [0f32; C];
+            for (px, &w) in src_row[first * C..].chunks_exact(C).zip(weights) {
+                for c in 0..C {
+                    acc[c] += w * px[c] as f32;
+                }
+            }
+            out.copy_from_slice(&acc);
+        }
+    }
+}
+
+pub fn packed_to_planar<T: InputElement>(
+    src: &[u8],
+    (w, h): (usize, usize),
+    channels: usize,
+    dst: &mut [T],
+    (dst_w, dst_h): (usize, usize),
+    (x0, y0): (usize, usize),
+    spec: &InputSpec,
+) {
+    // packed RGB(A) rows -> a (3, dst_h, dst_w) plane set at offset (x0, y0), alpha dropped
+    assert!(channels >= 3 && x0 + w <= dst_w && y0 + h <= dst_h);
+    assert_eq!(
+        dst.len(),
+        3 * dst_w * dst_h,
+        "planar destination size mismatch"
+    );
+    let lut = spec.lut::<T>();
+    let [i0, i1, i2] = spec.order.indices();
+    let plane = dst_w * dst_h;
+    let (p0, p12) = dst.split_at_mut(plane);
+    let (p1, p2) = p12.split_at_mut(plane);
+    for (y, src_row) in src.chunks_exact(w * channels).take(h).enumerate() {
+        let start = (y0 + y) * dst_w + x0;
+        let range = start..start + w;
+        for (((px, x0), x1), x2) in src_row
+            .chunks_exact(channels)
+            .zip(&mut p0[range.clone()])
+            .zip(&mut p1[range.clone()])
+            .zip(&mut p2[range])
+        {
+            *x0 = lut[0][px[i0] as usize];
+            *x1 = lut[1][px[i1] as usize];
+            *x2 = lut[2][px[i2] as usize];
+        }
+    }
+}
+
+pub fn packed_to_interleaved<T: InputElement>(
+    src: &[u8],
+    (w, h): (usize, usize),
+    channels: usize,
+    dst: &mut [T],
+    (dst_w, dst_h): (usize, usize),
+    (x0, y0): (usize, usize),
+    spec: &InputSpec,
+) {
+    // packed RGB(A) rows -> a (dst_h, dst_w, 3) image at offset (x0, y0), alpha dropped
+    assert!(channels >= 3 && x0 + w <= dst_w && y0 + h <= dst_h);
+    assert_eq!(
+        dst.len(),
+        3 * dst_w * dst_h,
+        "interleaved destination size mismatch"
+    );
+    let lut = spec.lut::<T>();
+    let [i0, i1, i2] = spec.order.indices();
+    for (y, src_row) in src.chunks_exact(w * channels).take(h).enumerate() {
+        let start = 3 * ((y0 + y) * dst_w + x0);
+        for (px, out) in src_row
+            .chunks_exact(channels)
+            .zip(dst[start..start + 3 * w].chunks_exact_mut(3))
+        {
+            out[0] = lut[0][px[i0] as usize];
+            out[1] = lut[1][px[i1] as usize];
+            out[2] = lut[2][px[i2] as usize];
+        }
+    }
+}
+
+pub fn fill_pad<T: InputElement>(
+    dst: &mut [T],
+    (w, h): (usize, usize),
+    spec: &InputSpec,
+    color: [u8; 3],
+) {
+    // whole input buffer (any batch size) -> pad colour
+    let pad = spec.pad_values::<T>(color);
+    match spec.layout {
+        Layout::Nchw => {
+            for (c, plane) in dst.chunks_exact_mut(w * h).enumerate() {
+                plane.fill(pad[c % 3]);
+            }
+        }
+        Layout::Nhwc => {
+            for px in dst.chunks_exact_mut(3) {
+                px.copy_from_slice(&pad);
+            }
+        }
+    }
+}
+
+pub fn letterbox_into<T: InputElement>(
+    img: &DynamicImage,
+    lb: &Letterbox,
+    resizer: &mut Resizer,
+    dst: &mut [T],
+    (w, h): (usize, usize),
+    spec: &InputSpec,
+) {
+    // one frame -> one slot of the input tensor, padding is left untouched
+    let (w0, h0) = img.dimensions();
+
+    // packed pixels, only uncommon formats get converted
+    let converted;
+    let (pixels, channels) = match img {
+        DynamicImage::ImageRgba8(img) => (img.as_raw().as_slice(), 4),
+        DynamicImage::ImageRgb8(img) => (img.as_raw().as_slice(), 3),
+        img => {
+            converted = img.to_rgba8();
+            (converted.as_raw().as_slice(), 4)
+        }
+    };
+
+    let size = (lb.width as usize, lb.height as usize);
+    let resized = resizer.resize(pixels, (w0 as usize, h0 as usize), channels, size);
+    let pack = match spec.layout {
+        Layout::Nchw => packed_to_planar::<T>,
+        Layout::Nhwc => packed_to_interleaved::<T>,
+    };
+    pack(
+        resized,
+        size,
+        channels,
+        dst,
+        (w, h),
+        (lb.pad_x as usize, lb.pad_y as usize),
+        spec,
+    );
+}
+
+pub fn unletterbox(
+    src: ArrayView2<f32>,
+    (ox, oy): (usize, usize),
+    (sx, sy): (f32, f32),
+    lb: &Letterbox,
+    (x0, y0, x1, y1): (u32, u32, u32, u32),
+    dst: &mut Mask,
+    threshold: Option<f32>,
+) {
+    // bilinear sample of a model-space grid at frame pixel centres, [x0, x1) x [y0, y1)
+    // `src` starts at cell (ox, oy) of a grid with (sx, sy) cells per model input pixel
+    let (rh, rw) = src.dim();
+    if rh == 0 || rw == 0 {
+        return;
+    }
+    let to_grid = |x: f32, y: f32| {
+        let (x, y) = lb.to_input(x, y);
+        (x * sx, y * sy)
+    };
+    // index and weight of the lower cell
+    let sample = |p: f32, p0: usize, n: usize| {
+        let p = (p - 0.5 - p0 as f32).clamp(0.0, (n - 1) as f32);
+        let i = (p.floor() as usize).min(n.saturating_sub(2));
+        (i, (p - i as f32).min(1.0))
+    };
+    let xs: Vec<_> = (x0..x1)
+        .map(|x| sample(to_grid(x as f32 + 0.5, 0.0).0, ox, rw))
+        .collect();
+    for y in y0..y1 {
+        let (iy, ty) = sample(to_grid(0.0, y as f32 + 0.5).1, oy, rh);
+        let iy1 = (iy + 1).min(rh - 1);
+        for (x, &(ix, tx)) in (x0..x1).zip(&xs) {
+            let ix1 = (ix + 1).min(rw - 1);
+            let top = src[[iy, ix]] * (1.0 - tx) + src[[iy, ix1]] * tx;
+            let bottom = src[[iy1, ix]] * (1.0 - tx) + src[[iy1, ix1]] * tx;
+            let p = top * (1.0 - ty) + bottom * ty;
+            dst.put_pixel(
+                x,
+                y,
+                Luma([match threshold {
+                    Some(t) => (p >= t) as u8 as f32,
+                    None => p,
+                }]),
+            );
+        }
+    }
+}
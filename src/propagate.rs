@@ -0,0 +1,76 @@
+use clap::ValueEnum;
+
+use crate::{Bbox, Mask, Point2};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Propagation {
+    // how the last mask follows the person on frames without inference
+    // none: reuse it as is
+    // bbox: shift it by the bbox velocity between the last two inferences
+    // flow: warp it with dense optical flow between consecutive frames
+    None,
+    Bbox,
+    #[default]
+    Flow,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BboxMotion {
+    // constant velocity of the bbox centre, in pixels per frame
+    last: Option<(u64, Point2)>,
+    velocity: (f32, f32),
+}
+
+impl BboxMotion {
+    pub fn update(&mut self, frame: u64, bbox: &Bbox) {
+        let cxcy = bbox.cxcy();
+        if let Some((f, p)) = &self.last {
+            if frame > *f {
+                let dt = (frame - f) as f32;
+                self.velocity = ((cxcy.x() - p.x()) / dt, (cxcy.y() - p.y()) / dt);
+            }
+        }
+        self.last = Some((frame, cxcy));
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn offset(&self, frame: u64) -> (f32, f32) {
+        // expected displacement since the last update
+        match &self.last {
+            Some((f, _)) => {
+                let dt = frame.saturating_sub(*f) as f32;
+                (self.velocity.0 * dt, self.velocity.1 * dt)
+            }
+            None => (0.0, 0.0),
+        }
+    }
+}
+
+pub fn translate(src: &Mask, (dx, dy): (f32, f32), dst: &mut Mask) {
+    // whole-pixel shift of `src` into `dst`, uncovered pixels are 0
+    let (w, h) = src.dimensions();
+    assert_eq!((w, h), dst.dimensions());
+    let (dx, dy) = (dx.round() as i64, dy.round() as i64);
+    let (w, h) = (w as i64, h as i64);
+    let (src, dst) = (src.as_raw(), &mut **dst);
+    dst.fill(0.0);
+
+    // destination columns that come from inside `src`
+    let (x0, x1) = (dx.clamp(0, w), (w + dx).clamp(0, w));
+    if x0 >= x1 {
+        return;
+    }
+    for y in 0..h {
+        let sy = y - dy;
+        if !(0..h).contains(&sy) {
+            continue;
+        }
+        let row = (y * w) as usize;
+        let src_row = (sy * w) as usize;
+        dst[row + x0 as usize..row + x1 as usize]
+            .copy_from_slice(&src[src_row + (x0 - dx) as usize..src_row + (x1 - dx) as usize]);
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ValueEnum};
+use serde::Deserialize;
+
+use crate::{Args, Error, SegmenterKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManifestDtype {
+    // precision the model should run at, f16 turns on `--fp16` for TensorRT
+    #[serde(alias = "fp32", alias = "float32")]
+    F32,
+    #[serde(alias = "fp16", alias = "float16")]
+    F16,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelManifest {
+    // `<models-dir>/<name>.toml`, every field but `path` is optional:
+    //
+    //   path = "yolov8n-seg.onnx"   # relative to the manifest
+    //   task = "yolo"               # --segmenter
+    //   width = 640
+    //   height = 640
+    //   dtype = "f16"
+    //   nc = 80
+    //   conf = 0.4
+    //   iou = 0.5
+    //   mask_threshold = 0.5
+    //   classes = ["person"]
+    //   names = "coco.names"        # relative to the manifest
+    //   description = "fast, for the laptop"
+    #[serde(skip)]
+    pub name: String,
+    pub path: PathBuf,
+    pub task: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub dtype: Option<ManifestDtype>,
+    pub nc: Option<u32>,
+    pub conf: Option<f32>,
+    pub iou: Option<f32>,
+    pub mask_threshold: Option<f32>,
+    pub classes: Option<Vec<String>>,
+    pub names: Option<PathBuf>,
+    pub description: Option<String>,
+}
+
+impl ModelManifest {
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let error = |reason: String| {
+            Error::Config(format!("Bad manifest `{}`: {}", path.display(), reason))
+        };
+        let s = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        let mut x: Self = toml::from_str(&s).map_err(|e| error(e.to_string()))?;
+        if let Some(task) = &x.task {
+            SegmenterKind::from_str(task, true).map_err(error)?;
+        }
+
+        // relative paths are relative to the manifest
+        let dir = path.parent().unwrap_or(Path::new("."));
+        x.path = dir.join(&x.path);
+        x.names = x.names.map(|names| dir.join(names));
+        x.name = path
+            .file_stem()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(x)
+    }
+
+    pub fn apply(&self, args: &mut Args, explicit: impl Fn(&str) -> bool) {
+        // manifest values fill in everything not given on the command line
+        args.model = self.path.to_string_lossy().into_owned();
+        let task = self.task.as_deref();
+        if let Some(task) = task.and_then(|x| SegmenterKind::from_str(x, true).ok()) {
+            if !explicit("segmenter") {
+                args.segmenter = task;
+            }
+        }
+        if !explicit("width") && self.width.is_some() {
+            args.width = self.width;
+        }
+        if !explicit("height") && self.height.is_some() {
+            args.height = self.height;
+        }
+        if !explicit("fp16") && self.dtype.is_some() {
+            args.fp16 = self.dtype == Some(ManifestDtype::F16);
+        }
+        if !explicit("nc") && self.nc.is_some() {
+            args.nc = self.nc;
+        }
+        if let (false, Some(conf)) = (explicit("conf"), self.conf) {
+            args.conf = conf;
+        }
+        if let (false, Some(iou)) = (explicit("iou"), self.iou) {
+            args.iou = iou;
+        }
+        if !explicit("mask_threshold") && self.mask_threshold.is_some() {
+            args.mask_threshold = self.mask_threshold;
+        }
+        if let (false, Some(classes)) = (explicit("classes"), &self.classes) {
+            args.classes = classes.clone();
+        }
+        if !explicit("names") && self.names.is_some() {
+            args.names = self
+                .names
+                .as_ref()
+                .map(|x| x.to_string_lossy().into_owned());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModelRegistry {
+    // every `*.toml` manifest in a directory, by file stem
+    models: BTreeMap<String, ModelManifest>,
+}
+
+impl ModelRegistry {
+    pub fn open(dir: &str) -> Result<Self, Error> {
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| Error::Config(format!("Failed to read models dir `{}`: {}", dir, e)))?;
+        let mut models = BTreeMap::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|x| x == "toml") {
+                let x = ModelManifest::from_file(&path)?;
+                models.insert(x.name.clone(), x);
+            }
+        }
+        Ok(Self { models })
+    }
+
+    pub fn get(&self, name: &str) -> Result<&ModelManifest, Error> {
+        self.models.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.models.keys().map(String::as_str).collect();
+            Error::Config(format!(
+                "Unknown model `{}`, available: [{}]",
+                name,
+                known.join(", ")
+            ))
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ModelManifest> {
+        self.models.values()
+    }
+
+    pub fn resolve(args: &mut Args, matches: &ArgMatches) -> Result<(), Error> {
+        // `--model-name` -> manifest values, flags given on the command line win
+        let Some(name) = args.model_name.clone() else {
+            return Ok(());
+        };
+        let registry = Self::open(&args.models_dir)?;
+        let explicit = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        registry.get(&name)?.apply(args, explicit);
+        Ok(())
+    }
+
+    pub fn list(&self) {
+        if self.models.is_empty() {
+            println!("No model manifests found");
+        }
+        for x in self.iter() {
+            let task = x.task.as_deref().unwrap_or("yolo");
+            let size = match (x.width, x.height) {
+                (Some(w), Some(h)) => format!(" {}x{}", w, h),
+                _ => String::new(),
+            };
+            let missing = if x.path.is_file() { "" } else { " (missing)" };
+            println!(
+                "{:<24} {}{} {}{}",
+                x.name,
+                task,
+                size,
+                x.path.display(),
+                missing
+            );
+            if let Some(description) = &x.description {
+                println!("{:<24} {}", "", description);
+            }
+        }
+    }
+}
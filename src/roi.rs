@@ -0,0 +1,101 @@
+use anyhow::Result;
+use image::{imageops, DynamicImage, GenericImageView};
+
+use crate::{Bbox, Mask, Matte, Segmenter, StageTimes};
+
+pub struct RoiSegmenter {
+    // runs `inner` on a padded crop around the previous person at full resolution,
+    // the whole frame is used until someone is found and whenever tracking is lost
+    inner: Box<dyn Segmenter>,
+    padding: f32,
+    track: Option<Bbox>,
+    // summed over both runs when the crop loses the person
+    times: Option<StageTimes>,
+}
+
+impl RoiSegmenter {
+    // crops covering most of the frame are not worth the copy
+    const MAX_COVERAGE: f32 = 0.9;
+
+    pub fn new(inner: Box<dyn Segmenter>, padding: f32) -> Self {
+        Self {
+            inner,
+            padding,
+            track: None,
+            times: None,
+        }
+    }
+
+    pub fn roi(&self, (w0, h0): (u32, u32)) -> Option<(u32, u32, u32, u32)> {
+        // previous bbox padded on every side by `padding` of its size, clipped to the frame
+        let bbox = self.track.as_ref()?;
+        let (px, py) = (bbox.width() * self.padding, bbox.height() * self.padding);
+        let x0 = (bbox.xmin() - px).floor().clamp(0.0, w0 as f32) as u32;
+        let y0 = (bbox.ymin() - py).floor().clamp(0.0, h0 as f32) as u32;
+        let x1 = (bbox.xmax() + px).ceil().clamp(0.0, w0 as f32) as u32;
+        let y1 = (bbox.ymax() + py).ceil().clamp(0.0, h0 as f32) as u32;
+        let (w, h) = (x1.saturating_sub(x0), y1.saturating_sub(y0));
+        if w < 2 || h < 2 || (w * h) as f32 > Self::MAX_COVERAGE * (w0 * h0) as f32 {
+            return None;
+        }
+        Some((x0, y0, w, h))
+    }
+
+    pub fn track(&self) -> Option<&Bbox> {
+        self.track.as_ref()
+    }
+}
+
+impl Segmenter for RoiSegmenter {
+    fn segment(&mut self, img: &DynamicImage) -> Result<Option<Matte>> {
+        let (w0, h0) = img.dimensions();
+        self.times = None;
+        if let Some((x, y, w, h)) = self.roi((w0, h0)) {
+            let crop = img.crop_imm(x, y, w, h);
+            let matte = self.inner.segment(&crop)?;
+            self.times = self.inner.stage_times();
+            if let Some(matte) = matte {
+                // crop -> frame
+                let mut alpha = Mask::new(w0, h0);
+                imageops::replace(&mut alpha, &matte.alpha, x as i64, y as i64);
+                let bbox = matte.bounds().map(|bbox| Bbox {
+                    xmin: bbox.xmin + x as f32,
+                    ymin: bbox.ymin + y as f32,
+                    ..bbox
+                });
+                self.track = bbox.clone();
+                return Ok(Some(Matte { alpha, bbox }));
+            }
+            // lost in the crop, try the whole frame right away
+        }
+
+        let matte = self.inner.segment(img)?;
+        self.times = match (self.times, self.inner.stage_times()) {
+            (Some(mut times), Some(more)) => {
+                times += more;
+                Some(times)
+            }
+            (times, more) => more.or(times),
+        };
+        self.track = matte.as_ref().and_then(Matte::bounds);
+        Ok(matte)
+    }
+
+    fn reset(&mut self) {
+        self.track = None;
+        self.inner.reset();
+    }
+
+    fn stage_times(&self) -> Option<StageTimes> {
+        self.times
+    }
+
+    fn end_profiling(&self) -> Result<Option<String>> {
+        self.inner.end_profiling()
+    }
+
+    fn summary(&self) {
+        self.inner.summary();
+        println!("> ROI: padding {}\n", self.padding);
+    }
+}
@@ -0,0 +1,288 @@
+use anyhow::{bail, Result};
+use image::{DynamicImage, GenericImageView};
+use ndarray::{arr1, Array, Axis, Ix2, IxDyn};
+
+use crate::{
+    unletterbox, Args, InputSpec, InputTensor, Letterbox, Mask, Matte, OrtBackend, OrtConfig,
+    Recurrent, Resizer, Segmenter, StageTimes,
+};
+
+pub struct VideoMatting {
+    // Robust Video Matting, recurrent states r1..r4 carried between frames
+    // inputs: src, r1i..r4i, downsample_ratio; outputs: fgr, pha, r1o..r4o
+    engine: OrtBackend,
+    height: u32,
+    width: u32,
+    mask_threshold: Option<f32>,
+    profile: bool,
+    times: StageTimes,
+    center: bool,
+    pad_color: [u8; 3],
+    spec: InputSpec,
+    xs: InputTensor,
+    resizer: Resizer,
+    src: String,
+    pha: usize,
+    ratio: Array<f32, IxDyn>,
+    state: Recurrent,
+    scene_cut: SceneCut,
+}
+
+impl VideoMatting {
+    pub fn new(config: Args) -> Result<Self> {
+        let engine = OrtBackend::build(OrtConfig::from_args(&config))?;
+        let (height, width) = (engine.height(), engine.width());
+
+        // every `*i` input with a matching `*o` output is recurrent
+        let output_names = engine.output_names();
+        let links: Vec<(String, String)> = engine
+            .input_names()
+            .iter()
+            .filter_map(|input| {
+                let output = format!("{}o", input.strip_suffix('i')?);
+                output_names
+                    .contains(&output)
+                    .then(|| (output, input.clone()))
+            })
+            .collect();
+        let links: Vec<(&str, &str)> = links
+            .iter()
+            .map(|(o, i)| (o.as_str(), i.as_str()))
+            .collect();
+        let state = engine.recurrent(&links)?;
+        let Some(pha) = output_names.iter().position(|x| x == "pha") else {
+            bail!("Model missing `pha` output, got {:?}", output_names);
+        };
+
+        // RVM recommends keeping the internal resolution around 512px
+        let ratio = config
+            .downsample_ratio
+            .unwrap_or_else(|| (512.0 / height.max(width) as f32).min(1.0));
+
+        // letterbox and input normalisation: cli > metadata > defaults
+        let pad_color = config.pad_rgb()?;
+        let spec = InputSpec::from_metadata(|key| engine.fetch_from_metadata(key))
+            .with_layout(engine.layout())
+            .with_args(&config)?;
+        let xs = InputTensor::zeros(
+            engine.dtype(),
+            &spec.layout.shape(1, height as usize, width as usize),
+        )?;
+
+        Ok(Self {
+            src: engine.input_names()[0].clone(),
+            engine,
+            height,
+            width,
+            mask_threshold: config.mask_threshold,
+            profile: config.profile,
+            times: StageTimes::default(),
+            center: config.center,
+            pad_color,
+            xs,
+            spec,
+            resizer: Resizer::new(config.resize),
+            pha,
+            ratio: arr1(&[ratio]).into_dyn(),
+            state,
+            scene_cut: SceneCut::new(config.scene_cut),
+        })
+    }
+
+    pub fn letterbox(&self, w0: u32, h0: u32) -> Letterbox {
+        Letterbox::new((w0, h0), (self.width, self.height), self.center)
+    }
+
+    pub fn preprocess(&mut self, img: &DynamicImage) -> Result<()> {
+        self.xs.letterbox(
+            std::slice::from_ref(img),
+            (self.width as usize, self.height as usize),
+            &self.spec,
+            self.pad_color,
+            self.center,
+            &mut self.resizer,
+        );
+        Ok(())
+    }
+
+    pub fn postprocess(&self, xs: &[Array<f32, IxDyn>], xs0: &DynamicImage) -> Result<Matte> {
+        // pha: [1, 1, h, w]
+        let pha = &xs[self.pha];
+        let &[1, 1, nh, nw] = pha.shape() else {
+            bail!(
+                "Expected `pha` of shape [1, 1, h, w], got {:?}",
+                pha.shape()
+            );
+        };
+        let pha = pha
+            .view()
+            .index_axis_move(Axis(0), 0)
+            .index_axis_move(Axis(0), 0)
+            .into_dimensionality::<Ix2>()?;
+
+        let (w0, h0) = xs0.dimensions();
+        let mut mask = Mask::new(w0, h0);
+        unletterbox(
+            pha,
+            (0, 0),
+            (
+                nw as f32 / self.width as f32,
+                nh as f32 / self.height as f32,
+            ),
+            &self.letterbox(w0, h0),
+            (0, 0, w0, h0),
+            &mut mask,
+            self.mask_threshold,
+        );
+
+        Ok(Matte {
+            alpha: mask,
+            bbox: None,
+        })
+    }
+
+    pub fn reset(&mut self) {
+        self.state.reset();
+    }
+
+    pub fn engine(&self) -> &OrtBackend {
+        &self.engine
+    }
+}
+
+impl Segmenter for VideoMatting {
+    fn segment(&mut self, img: &DynamicImage) -> Result<Option<Matte>> {
+        // stale hidden state smears the previous scene into the new one
+        if self.scene_cut.update(img) {
+            if self.profile {
+                println!("[Model Scene cut]: state reset");
+            }
+            self.reset();
+        }
+
+        self.times = StageTimes::default();
+
+        // pre-process
+        let t_pre = std::time::Instant::now();
+        self.preprocess(img)?;
+        self.times.preprocess += t_pre.elapsed();
+        if self.profile {
+            println!("[Model Preprocess]: {:?}", t_pre.elapsed());
+        }
+
+        // run
+        let t_run = std::time::Instant::now();
+        self.engine.run_recurrent(
+            &[
+                (self.src.as_str(), self.xs.view()),
+                ("downsample_ratio", self.ratio.view().into()),
+            ],
+            &mut self.state,
+            self.profile,
+        )?;
+        self.times.inference += t_run.elapsed();
+        if self.profile {
+            println!("[Model Inference]: {:?}", t_run.elapsed());
+        }
+
+        // post-process
+        let t_post = std::time::Instant::now();
+        let ys = self.postprocess(self.engine.outputs(), img)?;
+        self.times.postprocess += t_post.elapsed();
+        if self.profile {
+            println!("[Model Postprocess]: {:?}", t_post.elapsed());
+        }
+
+        Ok(Some(ys))
+    }
+
+    fn reset(&mut self) {
+        VideoMatting::reset(self)
+    }
+
+    fn stage_times(&self) -> Option<StageTimes> {
+        Some(self.times)
+    }
+
+    fn end_profiling(&self) -> Result<Option<String>> {
+        self.engine.end_profiling()
+    }
+
+    fn summary(&self) {
+        println!(
+            "\nSummary:\n\
+            > Robust video matting\n\
+            > EP: {:?}{}\n\
+            > Dtype: {:?}\n\
+            > Height: {}, Width: {}, Layout: {:?}\n\
+            > Downsample ratio: {}\n\
+            > Recurrent states: {:?}\n\
+            ",
+            self.engine.ep(),
+            if self.engine.ep_confirmed() {
+                ""
+            } else {
+                " (unconfirmed)"
+            },
+            self.engine.dtype(),
+            self.height,
+            self.width,
+            self.spec.layout,
+            self.ratio[0],
+            self.engine
+                .input_names()
+                .iter()
+                .filter(|x| x.as_str() != self.src && x.as_str() != "downsample_ratio")
+                .collect::<Vec<_>>(),
+        );
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SceneCut {
+    // mean absolute luma difference between coarse thumbnails of consecutive frames
+    threshold: f32,
+    prev: Vec<f32>,
+    next: Vec<f32>,
+}
+
+impl SceneCut {
+    const GRID: (u32, u32) = (32, 18);
+
+    pub fn new(threshold: f32) -> Self {
+        let n = (Self::GRID.0 * Self::GRID.1) as usize;
+        Self {
+            threshold,
+            prev: Vec::with_capacity(n),
+            next: Vec::with_capacity(n),
+        }
+    }
+
+    pub fn update(&mut self, img: &DynamicImage) -> bool {
+        // true when `img` starts a new scene, the first frame never does
+        let (w, h) = img.dimensions();
+        let (gw, gh) = Self::GRID;
+        self.next.clear();
+        for gy in 0..gh {
+            for gx in 0..gw {
+                let x = ((2 * gx + 1) * w / (2 * gw)).min(w.saturating_sub(1));
+                let y = ((2 * gy + 1) * h / (2 * gh)).min(h.saturating_sub(1));
+                let [r, g, b, _] = img.get_pixel(x, y).0;
+                let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+                self.next.push(luma / 255.0);
+            }
+        }
+
+        let cut = self.prev.len() == self.next.len()
+            && self
+                .prev
+                .iter()
+                .zip(&self.next)
+                .map(|(a, b)| (a - b).abs())
+                .sum::<f32>()
+                / self.next.len() as f32
+                > self.threshold;
+        std::mem::swap(&mut self.prev, &mut self.next);
+        cut
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
+use image::DynamicImage;
+
+use crate::{Args, Backend, Bbox, Mask, RoiSegmenter, StageTimes, YOLOv8};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SegmenterKind {
+    // yolo: instance segmentation, the best `person` mask is used
+    // matting: portrait matting models that output an alpha matte directly
+    // rvm: robust video matting, recurrent state carried between frames
+    #[default]
+    Yolo,
+    Matting,
+    Rvm,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matte {
+    // per-pixel person alpha, full frame size
+    pub alpha: Mask,
+    pub bbox: Option<Bbox>,
+}
+
+impl Matte {
+    pub fn bounds(&self) -> Option<Bbox> {
+        // the detector's bbox, or the extent of alpha >= 0.5 for matting models
+        if let Some(bbox) = &self.bbox {
+            return Some(bbox.clone());
+        }
+        let (mut x0, mut y0, mut x1, mut y1) = (u32::MAX, u32::MAX, 0, 0);
+        for (x, y, p) in self.alpha.enumerate_pixels() {
+            if p.0[0] >= 0.5 {
+                (x0, y0) = (x0.min(x), y0.min(y));
+                (x1, y1) = (x1.max(x + 1), y1.max(y + 1));
+            }
+        }
+        (x0 < x1).then(|| {
+            Bbox::new(
+                x0 as f32,
+                y0 as f32,
+                (x1 - x0) as f32,
+                (y1 - y0) as f32,
+                0,
+                1.0,
+            )
+        })
+    }
+}
+
+pub trait Segmenter: Send {
+    // frame -> person alpha matte, `None` when nobody is found
+    fn segment(&mut self, img: &DynamicImage) -> Result<Option<Matte>>;
+
+    // forget any state carried between frames
+    fn reset(&mut self) {}
+
+    // where the last `segment` call spent its time, for `bench`
+    fn stage_times(&self) -> Option<StageTimes> {
+        None
+    }
+
+    // write out `--ort-profile`, call before exiting, the trace stays empty otherwise
+    fn end_profiling(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn summary(&self);
+}
+
+pub fn build_segmenter(config: Args) -> Result<Box<dyn Segmenter>> {
+    let (roi, roi_padding) = (config.roi, config.roi_padding);
+    if config.backend != Backend::Ort && config.segmenter != SegmenterKind::Yolo {
+        bail!(
+            "`--backend {:?}` only runs `--segmenter yolo`",
+            config.backend
+        );
+    }
+    let model: Box<dyn Segmenter> = match config.segmenter {
+        SegmenterKind::Yolo => Box::new(YOLOv8::new(config)?),
+        #[cfg(feature = "ort")]
+        SegmenterKind::Matting => Box::new(crate::PortraitMatting::new(config)?),
+        #[cfg(feature = "ort")]
+        SegmenterKind::Rvm => Box::new(crate::VideoMatting::new(config)?),
+        #[cfg(not(feature = "ort"))]
+        SegmenterKind::Matting | SegmenterKind::Rvm => {
+            bail!(
+                "`--segmenter {:?}` needs a build with the `ort` feature",
+                config.segmenter
+            )
+        }
+    };
+    Ok(if roi {
+        Box::new(RoiSegmenter::new(model, roi_padding))
+    } else {
+        model
+    })
+}
+
+impl Segmenter for YOLOv8 {
+    fn segment(&mut self, img: &DynamicImage) -> Result<Option<Matte>> {
+        let person = self
+            .names()
+            .iter()
+            .find_map(|(&id, name)| (name == "person").then_some(id))
+            .ok_or_else(|| anyhow!("Model missing `person` bbox name"))?;
+        let Some(ys) = self.run(img)? else {
+            return Ok(None);
+        };
+
+        // bboxes come out of NMS sorted, so this is the most confident person
+        let Some(idx) = ys.bboxes.iter().position(|bb| bb.id == person) else {
+            return Ok(None);
+        };
+        let bbox = ys.bboxes[idx].clone();
+        Ok(ys.masks.into_iter().nth(idx).map(|alpha| Matte {
+            alpha,
+            bbox: Some(bbox),
+        }))
+    }
+
+    fn stage_times(&self) -> Option<StageTimes> {
+        Some(YOLOv8::stage_times(self))
+    }
+
+    fn end_profiling(&self) -> Result<Option<String>> {
+        self.engine().end_profiling()
+    }
+
+    fn summary(&self) {
+        YOLOv8::summary(self)
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::{bail, Result};
+use half::f16;
+use image::{DynamicImage, GenericImageView};
+use ndarray::{Array, ArrayView, IxDyn};
+
+use crate::{fill_pad, letterbox_into, InputElement, InputSpec, Letterbox, Resizer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DType {
+    // tensor element type, the ONNX set, independent of the runtime
+    Float32,
+    Uint8,
+    Int8,
+    Uint16,
+    Int16,
+    Int32,
+    Int64,
+    String,
+    Bool,
+    Float16,
+    Float64,
+    Uint32,
+    Uint64,
+    Bfloat16,
+}
+
+impl DType {
+    pub fn from_onnx(x: u64) -> Option<Self> {
+        // TensorProto.DataType
+        Some(match x {
+            1 => DType::Float32,
+            2 => DType::Uint8,
+            3 => DType::Int8,
+            4 => DType::Uint16,
+            5 => DType::Int16,
+            6 => DType::Int32,
+            7 => DType::Int64,
+            8 => DType::String,
+            9 => DType::Bool,
+            10 => DType::Float16,
+            11 => DType::Float64,
+            12 => DType::Uint32,
+            13 => DType::Uint64,
+            16 => DType::Bfloat16,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum InputTensor {
+    // model input buffer, preprocessing writes the session's input dtype directly
+    F32(Array<f32, IxDyn>),
+    F16(Array<f16, IxDyn>),
+    U8(Array<u8, IxDyn>),
+    I8(Array<i8, IxDyn>),
+}
+
+#[derive(Debug, Clone)]
+pub enum TensorView<'a> {
+    F32(ArrayView<'a, f32, IxDyn>),
+    F16(ArrayView<'a, f16, IxDyn>),
+    U8(ArrayView<'a, u8, IxDyn>),
+    I8(ArrayView<'a, i8, IxDyn>),
+}
+
+impl InputTensor {
+    pub fn zeros(dtype: DType, shape: &[usize]) -> Result<Self> {
+        let shape = IxDyn(shape);
+        Ok(match dtype {
+            DType::Float32 => InputTensor::F32(Array::zeros(shape)),
+            DType::Float16 => InputTensor::F16(Array::from_elem(shape, f16::ZERO)),
+            DType::Uint8 => InputTensor::U8(Array::zeros(shape)),
+            DType::Int8 => InputTensor::I8(Array::zeros(shape)),
+            dtype => bail!("Unsupported input dtype: {:?}", dtype),
+        })
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        match self {
+            InputTensor::F32(x) => x.shape(),
+            InputTensor::F16(x) => x.shape(),
+            InputTensor::U8(x) => x.shape(),
+            InputTensor::I8(x) => x.shape(),
+        }
+    }
+
+    pub fn view(&self) -> TensorView<'_> {
+        match self {
+            InputTensor::F32(x) => TensorView::F32(x.view()),
+            InputTensor::F16(x) => TensorView::F16(x.view()),
+            InputTensor::U8(x) => TensorView::U8(x.view()),
+            InputTensor::I8(x) => TensorView::I8(x.view()),
+        }
+    }
+
+    pub fn letterbox(
+        &mut self,
+        xs: &[DynamicImage],
+        size: (usize, usize),
+        spec: &InputSpec,
+        pad_color: [u8; 3],
+        center: bool,
+        resizer: &mut Resizer,
+    ) {
+        // one frame per batch slot
+        match self {
+            InputTensor::F32(x) => {
+                letterbox_batch(slice(x), xs, size, spec, pad_color, center, resizer)
+            }
+            InputTensor::F16(x) => {
+                letterbox_batch(slice(x), xs, size, spec, pad_color, center, resizer)
+            }
+            InputTensor::U8(x) => {
+                letterbox_batch(slice(x), xs, size, spec, pad_color, center, resizer)
+            }
+            InputTensor::I8(x) => {
+                letterbox_batch(slice(x), xs, size, spec, pad_color, center, resizer)
+            }
+        }
+    }
+}
+
+impl TensorView<'_> {
+    pub fn dtype(&self) -> DType {
+        match self {
+            TensorView::F32(_) => DType::Float32,
+            TensorView::F16(_) => DType::Float16,
+            TensorView::U8(_) => DType::Uint8,
+            TensorView::I8(_) => DType::Int8,
+        }
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        match self {
+            TensorView::F32(x) => x.shape(),
+            TensorView::F16(x) => x.shape(),
+            TensorView::U8(x) => x.shape(),
+            TensorView::I8(x) => x.shape(),
+        }
+    }
+
+    pub fn view(&self) -> TensorView<'_> {
+        match self {
+            TensorView::F32(x) => TensorView::F32(x.view()),
+            TensorView::F16(x) => TensorView::F16(x.view()),
+            TensorView::U8(x) => TensorView::U8(x.view()),
+            TensorView::I8(x) => TensorView::I8(x.view()),
+        }
+    }
+}
+
+impl<'a> From<ArrayView<'a, f32, IxDyn>> for TensorView<'a> {
+    fn from(x: ArrayView<'a, f32, IxDyn>) -> Self {
+        TensorView::F32(x)
+    }
+}
+
+fn slice<T>(x: &mut Array<T, IxDyn>) -> &mut [T] {
+    x.as_slice_mut().expect("input buffer is contiguous")
+}
+
+fn letterbox_batch<T: InputElement>(
+    dst: &mut [T],
+    xs: &[DynamicImage],
+    (w, h): (usize, usize),
+    spec: &InputSpec,
+    pad_color: [u8; 3],
+    center: bool,
+    resizer: &mut Resizer,
+) {
+    // every slot starts out as pad colour, including the unused tail of a static batch
+    fill_pad(dst, (w, h), spec, pad_color);
+    for (img, ys) in xs.iter().zip(dst.chunks_exact_mut(3 * w * h)) {
+        let lb = Letterbox::new(img.dimensions(), (w as u32, h as u32), center);
+        letterbox_into(img, &lb, resizer, ys, (w, h), spec);
+    }
+}
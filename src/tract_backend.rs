@@ -0,0 +1,170 @@
+use anyhow::Result;
+use ndarray::{Array, IxDyn};
+use tract_onnx::prelude::*;
+
+use crate::onnx::{ModelInfo, ModelInputs};
+use crate::{
+    output_quantization, DType, Error, InferenceBackend, Layout, OrtConfig, OrtEP, Quantization,
+    TensorView,
+};
+
+pub struct TractBackend {
+    // pure-Rust CPU engine, the plan is compiled for one input shape
+    plan: TypedSimplePlan<TypedModel>,
+    info: ModelInfo,
+    dtype: DType,
+    layout: Layout,
+    batch: u32,
+    size: (u32, u32),
+    output_shapes: Vec<Vec<i32>>,
+    dequant: Vec<Quantization>,
+    outputs: Vec<Array<f32, IxDyn>>,
+    ep: OrtEP,
+}
+
+impl TractBackend {
+    pub fn build(args: &OrtConfig) -> Result<Self, Error> {
+        let load = |reason: String| Error::Load {
+            path: args.f.clone(),
+            reason,
+        };
+
+        // inputs and metadata straight from the file, the same rules as the ORT backend
+        let info = ModelInfo::read(&args.f).map_err(load)?;
+        let (inputs, batch, layout) =
+            ModelInputs::from_model(&info).resolve(args, |key| info.metadata(key))?;
+        let dtype = inputs.dtypes[0];
+        let datum = match dtype {
+            DType::Float32 => f32::datum_type(),
+            DType::Float16 => f16::datum_type(),
+            DType::Uint8 => u8::datum_type(),
+            DType::Int8 => i8::datum_type(),
+            dtype => {
+                return Err(Error::Dtype(format!(
+                    "Unsupported input dtype: {:?}, expected float32, float16, uint8 or int8",
+                    dtype
+                )))
+            }
+        };
+        let (height, width) = (inputs.sizes[0][0], inputs.sizes[0][1]);
+
+        // dynamic axes are pinned here: batch to `--batch`, size to `--height` / `--width`
+        let shape = layout.shape(batch.opt as usize, height as usize, width as usize);
+        let model = tract_onnx::onnx()
+            .model_for_path(&args.f)
+            .map_err(|e| load(format!("{:#}", e)))?
+            .with_input_fact(0, InferenceFact::dt_shape(datum, shape))
+            .and_then(|x| x.into_optimized())
+            .map_err(|e| Error::Shape(format!("{:#}", e)))?;
+        let output_shapes = (0..model.outputs.len())
+            .map(|i| {
+                let fact = model.output_fact(i)?;
+                Ok(fact
+                    .shape
+                    .iter()
+                    .map(|x| x.to_i64().map_or(-1, |x| x as i32))
+                    .collect())
+            })
+            .collect::<TractResult<Vec<Vec<i32>>>>()
+            .map_err(|e| Error::Shape(format!("{:#}", e)))?;
+        let dequant = output_quantization(output_shapes.len(), |key| info.metadata(key))?;
+        let plan = model
+            .into_runnable()
+            .map_err(|e| Error::Ep(format!("{:#}", e)))?;
+
+        Ok(Self {
+            plan,
+            info,
+            dtype,
+            layout,
+            batch: batch.opt,
+            size: (height, width),
+            outputs: vec![Array::zeros(IxDyn(&[0])); output_shapes.len()],
+            output_shapes,
+            dequant,
+            ep: OrtEP::Cpu,
+        })
+    }
+}
+
+impl InferenceBackend for TractBackend {
+    fn dtype(&self) -> DType {
+        self.dtype
+    }
+
+    fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    fn batch(&self) -> u32 {
+        self.batch
+    }
+
+    fn height(&self) -> u32 {
+        self.size.0
+    }
+
+    fn width(&self) -> u32 {
+        self.size.1
+    }
+
+    // shapes are fixed once the plan is built
+    fn is_batch_dynamic(&self) -> bool {
+        false
+    }
+
+    fn is_height_dynamic(&self) -> bool {
+        false
+    }
+
+    fn is_width_dynamic(&self) -> bool {
+        false
+    }
+
+    fn output_shapes(&self) -> Vec<Vec<i32>> {
+        self.output_shapes.clone()
+    }
+
+    fn fetch_from_metadata(&self, key: &str) -> Option<String> {
+        self.info.metadata(key)
+    }
+
+    fn ep(&self) -> &OrtEP {
+        &self.ep
+    }
+
+    fn run(&mut self, xs: TensorView, profile: bool) -> Result<()> {
+        // tract takes ownership of its inputs
+        let t = std::time::Instant::now();
+        let x: Tensor = match xs {
+            TensorView::F32(x) => x.to_owned().into(),
+            TensorView::F16(x) => x.to_owned().into(),
+            TensorView::U8(x) => x.to_owned().into(),
+            TensorView::I8(x) => x.to_owned().into(),
+        };
+        let ys = self.plan.run(tvec!(x.into()))?;
+        if profile {
+            println!("[tract Inference]: {:?}", t.elapsed());
+        }
+
+        // outputs as f32, integers dequantised as in ORT, buffers reused while the shape holds
+        for ((dst, y), q) in self.outputs.iter_mut().zip(ys).zip(&self.dequant) {
+            let integer = y.datum_type().is_integer();
+            let y = y.cast_to::<f32>()?;
+            let y = y.to_array_view::<f32>()?;
+            if dst.shape() != y.shape() {
+                *dst = Array::zeros(y.raw_dim());
+            }
+            if integer {
+                dst.zip_mut_with(&y, |x, &y| *x = q.dequantize(y));
+            } else {
+                dst.assign(&y);
+            }
+        }
+        Ok(())
+    }
+
+    fn outputs(&self) -> &[Array<f32, IxDyn>] {
+        &self.outputs
+    }
+}
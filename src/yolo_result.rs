@@ -1,13 +1,16 @@
 use image::Luma;
 use ndarray::{Array, Axis, IxDyn};
 
+// per-pixel mask probability (or 0/1 when binarised), full frame size
+pub type Mask = image::ImageBuffer<Luma<f32>, Vec<f32>>;
+
 #[derive(Clone, PartialEq, Default)]
 pub struct YOLOResult {
     // YOLO tasks results of an image
     pub probs: Option<Embedding>,
     pub bboxes: Vec<Bbox>,
     pub keypoints: Vec<Vec<Point2>>,
-    pub masks: Vec<image::ImageBuffer<Luma<u8>, Vec<u8>>>,
+    pub masks: Vec<Mask>,
 }
 
 impl std::fmt::Debug for YOLOResult {
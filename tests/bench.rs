@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use clap::{CommandFactory, FromArgMatches, Parser};
+use image::DynamicImage;
+use ndarray::Array3;
+use webcam_segmentation::{
+    synthetic_frame, Args, Bench, BenchFormat, Cli, MockBackend, Segmenter, Stats, SubCommand,
+    YOLOv8,
+};
+
+fn ms(x: u64) -> Duration {
+    Duration::from_millis(x)
+}
+
+#[test]
+fn percentiles_are_nearest_rank() {
+    let xs: Vec<Duration> = (1..=100).rev().map(ms).collect();
+    let x = Stats::from_samples(&xs).unwrap();
+    assert_eq!(x.n, 100);
+    assert_eq!(
+        (x.min, x.p50, x.p95, x.p99),
+        (ms(1), ms(50), ms(95), ms(99))
+    );
+    assert_eq!(x.mean, Duration::from_micros(50_500));
+
+    let x = Stats::from_samples(&[ms(7)]).unwrap();
+    assert_eq!((x.min, x.mean, x.p50, x.p99), (ms(7), ms(7), ms(7), ms(7)));
+    assert!(Stats::from_samples(&[]).is_none());
+}
+
+#[test]
+fn reports_stages_in_order() {
+    let mut bench = Bench::default();
+    for i in 1..=4 {
+        bench.record("decode", ms(i));
+        assert_eq!(bench.time("inference", || i * 2), i * 2);
+        bench.record("encode", ms(2 * i));
+    }
+    let names: Vec<_> = bench.stats().iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["decode", "inference", "encode"]);
+
+    let table = bench.report(BenchFormat::Table);
+    assert_eq!(table.lines().count(), 4);
+    assert!(table.lines().nth(1).unwrap().starts_with("decode"));
+    assert!(table.contains("2.500"), "{}", table);
+
+    let json = bench.report(BenchFormat::Json);
+    assert!(json.starts_with("{\"stages\":[{\"stage\":\"decode\",\"n\":4,\"min_ms\":1.0000,"));
+    assert!(json.contains("\"stage\":\"encode\",\"n\":4,\"min_ms\":2.0000,\"mean_ms\":5.0000,"));
+    assert!(json.ends_with("}]}"));
+}
+
+#[test]
+fn segmenters_report_stage_times() {
+    let preds = Array3::<f32>::zeros((1, 5, 3)).into_dyn();
+    let engine = MockBackend::new(64, 64)
+        .with_metadata("names", "{0: 'person'}")
+        .with_outputs(vec![preds]);
+    let args = Args::parse_from(["test", "--model", "mock.onnx", "--source", "/dev/null"]);
+    let mut model = YOLOv8::with_backend(Box::new(engine), args).unwrap();
+    assert_eq!(Segmenter::stage_times(&model), Some(Default::default()));
+
+    let img = DynamicImage::ImageRgba8(synthetic_frame(128, 72));
+    model.segment(&img).unwrap();
+    let times = Segmenter::stage_times(&model).unwrap();
+    assert!(times.preprocess > Duration::ZERO);
+}
+
+#[test]
+fn synthetic_frames_are_not_flat() {
+    let frame = synthetic_frame(64, 32);
+    assert_eq!(frame.dimensions(), (64, 32));
+    assert_eq!(frame, synthetic_frame(64, 32));
+    assert_ne!(frame.get_pixel(0, 0), frame.get_pixel(63, 31));
+    assert!(frame.pixels().all(|p| p[3] == 255));
+}
+
+#[test]
+fn parses_the_bench_subcommand() {
+    let matches = Cli::command()
+        .try_get_matches_from([
+            "test", "--model", "m.onnx", "bench", "--frames", "10", "--format", "json",
+        ])
+        .unwrap();
+    let cli = Cli::from_arg_matches(&matches).unwrap();
+    let Some(SubCommand::Bench(opts)) = cli.command else {
+        panic!("expected `bench`");
+    };
+    assert_eq!(cli.args.model, "m.onnx");
+    assert_eq!((opts.frames, opts.format), (10, BenchFormat::Json));
+    assert_eq!((opts.frame_width, opts.frame_height), (1280, 720));
+    assert!(opts.input.is_none());
+}
@@ -0,0 +1,28 @@
+use clap::Parser;
+use webcam_segmentation::{Args, InputSpec};
+
+fn args(extra: &[&str]) -> Args {
+    let base = ["test", "--model", "m.onnx", "--source", "/dev/null"];
+    Args::parse_from(base.iter().chain(extra))
+}
+
+#[test]
+fn pad_color_takes_r_g_b() {
+    assert_eq!(args(&[]).pad_rgb().unwrap(), [144, 144, 144]);
+    assert_eq!(
+        args(&["--pad-color", "0,10,20"]).pad_rgb().unwrap(),
+        [0, 10, 20]
+    );
+    assert!(args(&["--pad-color", "0,10"]).pad_rgb().is_err());
+}
+
+#[test]
+fn mean_and_std_take_a_b_c() {
+    let x = args(&["--mean", "0.5,0.5,0.5", "--std", "0.25,0.25,0.25"]);
+    assert_eq!(x.mean, Some(vec![0.5; 3]));
+    assert_eq!(x.std, Some(vec![0.25; 3]));
+    assert!(InputSpec::default().with_args(&x).is_ok());
+    assert!(InputSpec::default()
+        .with_args(&args(&["--mean", "0.5,0.5"]))
+        .is_err());
+}
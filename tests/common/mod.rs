@@ -0,0 +1,8 @@
+// ONNX protobuf fixtures shared by the integration tests
+
+pub fn field(n: u8, bytes: &[u8]) -> Vec<u8> {
+    // length-delimited protobuf field, lengths here stay below 128
+    let mut x = vec![n << 3 | 2, bytes.len() as u8];
+    x.extend_from_slice(bytes);
+    x
+}
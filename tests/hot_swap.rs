@@ -0,0 +1,92 @@
+use anyhow::{bail, Result};
+use clap::Parser;
+use image::{DynamicImage, RgbImage};
+use webcam_segmentation::{hot_swap, Args, Command, Mask, Matte, Segmenter};
+
+struct Constant(f32);
+
+impl Segmenter for Constant {
+    // alpha is the same value everywhere, tells the models apart
+    fn segment(&mut self, img: &DynamicImage) -> Result<Option<Matte>> {
+        let alpha = Mask::from_pixel(img.width(), img.height(), image::Luma([self.0]));
+        Ok(Some(Matte { alpha, bbox: None }))
+    }
+
+    fn summary(&self) {}
+}
+
+fn alpha(model: &mut Box<dyn Segmenter>) -> f32 {
+    let img = DynamicImage::ImageRgb8(RgbImage::new(4, 4));
+    model.segment(&img).unwrap().unwrap().alpha.get_pixel(0, 0)[0]
+}
+
+fn wait<T>(mut f: impl FnMut() -> Option<T>) -> T {
+    // loads finish on their own thread
+    for _ in 0..500 {
+        if let Some(x) = f() {
+            return x;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    panic!("timed out");
+}
+
+#[test]
+fn swaps_in_loaded_models_between_frames() {
+    let (loader, swap) = hot_swap();
+    let mut model: Box<dyn Segmenter> = Box::new(Constant(0.25));
+    assert!(swap.poll(&mut model).is_none());
+
+    loader.load_with("accurate".to_string(), || Ok(Box::new(Constant(0.75))));
+    let name = wait(|| swap.poll(&mut model)).unwrap();
+    assert_eq!(name, "accurate");
+    assert_eq!(alpha(&mut model), 0.75);
+}
+
+#[test]
+fn failed_loads_keep_the_old_model() {
+    let (loader, swap) = hot_swap();
+    let mut model: Box<dyn Segmenter> = Box::new(Constant(0.25));
+    loader.load_with("broken.onnx".to_string(), || bail!("no such file"));
+    let err = wait(|| swap.poll(&mut model)).unwrap_err();
+    assert!(format!("{:#}", err).contains("broken.onnx"));
+    assert_eq!(alpha(&mut model), 0.25);
+}
+
+#[test]
+fn parses_load_commands() {
+    let base = Args::parse_from([
+        "test",
+        "--model",
+        "n.onnx",
+        "--source",
+        "/dev/video0",
+        "--fp16",
+        "--iou",
+        "0.6",
+    ]);
+    let Ok(Command::Load(args)) = Command::parse("load s.onnx --conf 0.5 --no-memory-arena", &base)
+    else {
+        panic!("expected `load`");
+    };
+    assert_eq!(args.model, "s.onnx");
+    assert_eq!(args.conf, 0.5);
+    assert!(args.no_memory_arena);
+    // everything else carries over from the running flags, not the defaults
+    assert_eq!(args.source, "/dev/video0");
+    assert!(args.fp16);
+    assert_eq!(args.iou, 0.6);
+
+    assert!(matches!(Command::parse(" quit ", &base), Ok(Command::Quit)));
+    assert!(Command::parse("load", &base).is_err());
+    assert!(Command::parse("unload n.onnx", &base).is_err());
+    assert!(Command::parse("load s.onnx --no-such-flag", &base).is_err());
+}
+
+#[test]
+fn quit_reaches_the_model_thread() {
+    let (loader, swap) = hot_swap();
+    assert!(!swap.quitting());
+    loader.clone().quit();
+    assert!(swap.quitting());
+}
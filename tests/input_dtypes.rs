@@ -0,0 +1,86 @@
+use half::f16;
+use ndarray::{Array, IxDyn};
+use webcam_segmentation::{fill_pad, DType, InputSpec, PixelRange, Quantization, TensorView};
+
+#[test]
+fn integer_inputs_take_raw_pixels_by_default() {
+    let spec = InputSpec::default();
+    let lut = spec.lut::<u8>();
+    assert!((0..256).all(|v| lut[0][v] as usize == v));
+
+    let lut = spec.lut::<i8>();
+    assert_eq!((lut[1][0], lut[1][128], lut[1][255]), (-128, 0, 127));
+
+    // same for 0-255 models
+    let spec = InputSpec {
+        range: PixelRange::Byte,
+        ..Default::default()
+    };
+    assert_eq!(spec.lut::<u8>()[2][200], 200);
+}
+
+#[test]
+fn explicit_quantization_parameters() {
+    // mean/std normalised, then quantised to int8 with scale 1/64
+    let spec = InputSpec {
+        mean: [0.5; 3],
+        std: [0.5; 3],
+        quant: Some(Quantization {
+            scale: 1.0 / 64.0,
+            zero_point: 0,
+        }),
+        ..Default::default()
+    };
+    let lut = spec.lut::<i8>();
+    // 0 -> -1.0 -> -64, 255 -> 1.0 -> 64
+    assert_eq!((lut[0][0], lut[0][255]), (-64, 64));
+
+    // saturates instead of wrapping
+    let spec = InputSpec {
+        quant: Some(Quantization {
+            scale: 1.0 / 512.0,
+            zero_point: 10,
+        }),
+        ..Default::default()
+    };
+    assert_eq!(spec.lut::<u8>()[0][255], 255);
+    assert_eq!(spec.lut::<i8>()[0][255], 127);
+
+    let q = Quantization {
+        scale: 0.5,
+        zero_point: -2,
+    };
+    assert_eq!(q.dequantize(q.quantize(3.0)), 3.0);
+}
+
+#[test]
+fn pad_writes_quantised_values() {
+    let spec = InputSpec::default();
+    let mut xs = vec![0i8; 3 * 2 * 2];
+    fill_pad(&mut xs, (2, 2), &spec, [0, 128, 255]);
+    assert_eq!(xs, [[-128; 4], [0; 4], [127; 4]].concat());
+}
+
+#[test]
+fn f16_inputs_match_f32() {
+    let spec = InputSpec {
+        mean: [0.485, 0.456, 0.406],
+        std: [0.229, 0.224, 0.225],
+        ..Default::default()
+    };
+    let (lut16, lut32) = (spec.lut::<f16>(), spec.lut::<f32>());
+    for c in 0..3 {
+        for v in 0..256 {
+            assert_eq!(lut16[c][v], f16::from_f32(lut32[c][v]));
+        }
+    }
+}
+
+#[test]
+fn views_report_dtype_and_shape() {
+    // what dtype mismatch errors print instead of the tensor itself
+    let x = Array::<u8, _>::zeros(IxDyn(&[1, 3, 4, 4]));
+    let view = TensorView::U8(x.view());
+    assert_eq!(view.dtype(), DType::Uint8);
+    assert_eq!(view.shape(), &[1, 3, 4, 4]);
+}
@@ -0,0 +1,185 @@
+use clap::Parser;
+use image::{DynamicImage, GenericImageView, RgbImage};
+use ndarray::{Array, Array3, IxDyn};
+use webcam_segmentation::{Args, Error, InferenceBackend, MockBackend, Segmenter, YOLOv8};
+
+fn args(extra: &[&str]) -> Args {
+    Args::parse_from(
+        ["test", "--model", "mock.onnx", "--source", "/dev/null"]
+            .iter()
+            .chain(extra),
+    )
+}
+
+fn outputs(batch: usize) -> Vec<Array<f32, IxDyn>> {
+    // one person, a lower scoring duplicate of it and a background anchor,
+    // on a 64x64 input with 1 class and 2 mask coefficients: [bs, 4 + 1 + 2, 3]
+    let anchors = [
+        [32.0, 32.0, 20.0, 40.0, 0.9, 4.0, 0.0],
+        [33.0, 32.0, 20.0, 40.0, 0.8, 4.0, 0.0],
+        [8.0, 8.0, 4.0, 4.0, 0.1, 4.0, 0.0],
+    ];
+    let mut preds = Array3::<f32>::zeros((batch, 7, 3));
+    for b in 0..batch {
+        for (a, anchor) in anchors.iter().enumerate() {
+            for (c, &x) in anchor.iter().enumerate() {
+                preds[[b, c, a]] = x;
+            }
+        }
+    }
+    // proto 0 is 1 everywhere, proto 1 never contributes
+    let mut protos = Array::zeros(IxDyn(&[batch, 2, 16, 16]));
+    protos.slice_mut(ndarray::s![.., 0, .., ..]).fill(1.0);
+    vec![preds.into_dyn(), protos]
+}
+
+fn frame() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::new(128, 128))
+}
+
+#[test]
+fn decodes_scripted_outputs() {
+    let engine = MockBackend::new(64, 64)
+        .with_metadata("names", "{0: 'person'}")
+        .with_outputs(outputs(1));
+    let mut model = YOLOv8::with_backend(Box::new(engine), args(&[])).unwrap();
+    assert_eq!((model.nc(), model.nm()), (1, 2));
+
+    let y = model.run(&frame()).unwrap().unwrap();
+    // the duplicate is suppressed, the background is under `--conf`
+    assert_eq!(y.bboxes.len(), 1);
+    let bbox = &y.bboxes[0];
+    // input is half the frame size
+    assert_eq!((bbox.xmin(), bbox.ymin()), (44.0, 24.0));
+    assert_eq!((bbox.width(), bbox.height()), (40.0, 80.0));
+
+    // sigmoid(4) inside the bbox, nothing far outside of it
+    let mask = &y.masks[0];
+    assert_eq!(mask.dimensions(), (128, 128));
+    assert!((mask.get_pixel(64, 64)[0] - 0.982).abs() < 1e-3);
+    assert_eq!(mask.get_pixel(2, 2)[0], 0.0);
+    assert_eq!(model.engine().outputs().len(), 2);
+}
+
+#[test]
+fn segments_the_person() {
+    let engine = MockBackend::new(64, 64)
+        .with_metadata("names", "{0: 'person'}")
+        .with_outputs(outputs(1));
+    let mut model =
+        YOLOv8::with_backend(Box::new(engine), args(&["--mask-threshold", "0.5"])).unwrap();
+    let img = frame();
+    let matte = model.segment(&img).unwrap().unwrap();
+    assert_eq!(matte.alpha.dimensions(), img.dimensions());
+    assert_eq!(matte.alpha.get_pixel(64, 64)[0], 1.0);
+    assert_eq!(matte.bounds().map(|x| x.xmin()), Some(44.0));
+}
+
+#[test]
+fn static_batch_is_padded() {
+    let engine = MockBackend::new(64, 64)
+        .with_static_batch(2)
+        .with_metadata("names", "{0: 'person'}")
+        .with_outputs(outputs(2));
+    let mut model = YOLOv8::with_backend(Box::new(engine), args(&["--batch", "2"])).unwrap();
+
+    // 3 frames -> 2 runs, each with a full batch, one result per frame
+    let ys = model.run_batch(&[frame(), frame(), frame()]).unwrap();
+    assert_eq!(ys.len(), 3);
+    assert!(ys.iter().all(|y| y.bboxes.len() == 1));
+}
+
+#[test]
+fn runs_without_a_script_fail() {
+    let mut engine = MockBackend::new(64, 64);
+    let xs = Array::zeros(IxDyn(&[1, 3, 64, 64]));
+    assert!(engine.run(xs.view().into(), false).is_err());
+    assert_eq!(engine.runs(), 1);
+    assert_eq!(engine.input_shapes()[0], vec![1, 3, 64, 64]);
+}
+
+#[test]
+fn out_of_range_names_are_reported() {
+    let engine = MockBackend::new(64, 64)
+        .with_metadata("names", "{0: 'person', 3: 'dog'}")
+        .with_outputs(outputs(1));
+    // 3 anchors are too few for shape based detection, pin the head
+    match YOLOv8::with_backend(Box::new(engine), args(&["--output-layout", "v8"])) {
+        Err(Error::Metadata { key, reason }) => {
+            assert_eq!(key, "names");
+            assert!(reason.contains("class id 3"), "{}", reason);
+        }
+        _ => panic!("expected a names error"),
+    }
+}
+
+#[test]
+fn warmup_runs_on_a_blank_frame() {
+    let engine = MockBackend::new(64, 64)
+        .with_metadata("names", "{0: 'person'}")
+        .with_outputs(outputs(1));
+    let mut model = YOLOv8::with_backend(Box::new(engine), args(&[])).unwrap();
+    assert_eq!(model.warmup(3).unwrap().len(), 3);
+    assert!(model.warmup(0).unwrap().is_empty());
+    assert_eq!(model.run(&frame()).unwrap().unwrap().bboxes.len(), 1);
+}
+
+#[test]
+fn warmup_checks_the_outputs() {
+    // the head changes after the first run, as a model with a bad export might
+    let mut no_protos = outputs(1);
+    no_protos.truncate(1);
+    let mut wide_head = outputs(1);
+    wide_head[0] = Array::zeros(IxDyn(&[1, 8, 3]));
+
+    for (ys, expected) in [(no_protos, "Mask prototypes"), (wide_head, "Output 0")] {
+        let engine = MockBackend::new(64, 64)
+            .with_metadata("names", "{0: 'person'}")
+            .with_outputs(outputs(1))
+            .with_outputs(ys);
+        let mut model = YOLOv8::with_backend(Box::new(engine), args(&[])).unwrap();
+        match model.warmup(2) {
+            Err(Error::Shape(reason)) => assert!(reason.contains(expected), "{}", reason),
+            _ => panic!("expected a shape error"),
+        }
+    }
+}
+
+#[test]
+fn detection_only_heads_have_no_masks() {
+    // no protos and no mask coefficients: YOLOv10 end-to-end [bs, 300, 6] and v8 [bs, 4 + 1, 3]
+    let mut e2e = Array3::<f32>::zeros((1, 300, 6));
+    for (c, &x) in [22.0, 12.0, 42.0, 52.0, 0.9, 0.0].iter().enumerate() {
+        e2e[[0, 0, c]] = x;
+    }
+    let mut v8 = Array3::<f32>::zeros((1, 5, 3));
+    for (c, &x) in [32.0, 32.0, 20.0, 40.0, 0.9].iter().enumerate() {
+        v8[[0, c, 0]] = x;
+    }
+
+    for preds in [e2e, v8] {
+        let engine = MockBackend::new(64, 64)
+            .with_metadata("names", "{0: 'person'}")
+            .with_outputs(vec![preds.into_dyn()]);
+        let mut model = YOLOv8::with_backend(Box::new(engine), args(&[])).unwrap();
+        assert_eq!(model.nm(), 0);
+        let y = model.run(&frame()).unwrap().unwrap();
+        assert_eq!(y.bboxes.len(), 1);
+        assert_eq!((y.bboxes[0].xmin(), y.bboxes[0].ymin()), (44.0, 24.0));
+        assert!(y.masks.is_empty());
+    }
+}
+
+#[test]
+fn missing_detection_output_is_a_shape_error() {
+    // no outputs at all, and a 2D output
+    for ys in [vec![], vec![Array::zeros(IxDyn(&[1, 7]))]] {
+        let engine = MockBackend::new(64, 64)
+            .with_metadata("names", "{0: 'person'}")
+            .with_outputs(ys);
+        match YOLOv8::with_backend(Box::new(engine), args(&[])) {
+            Err(Error::Shape(reason)) => assert!(reason.contains("3D"), "{}", reason),
+            _ => panic!("expected a shape error"),
+        }
+    }
+}
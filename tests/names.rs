@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+
+use webcam_segmentation::{parse_names, read_names};
+
+fn map(xs: &[(usize, &str)]) -> BTreeMap<usize, String> {
+    xs.iter().map(|&(id, x)| (id, x.to_string())).collect()
+}
+
+#[test]
+fn parses_python_dicts() {
+    let names = parse_names(
+        r#"{0: 'person', 1: "yellow_lady's_slipper", 2: 'traffic.light', 3: 'a/b', 10: '42', 11: 'it\'s'}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        names,
+        map(&[
+            (0, "person"),
+            (1, "yellow_lady's_slipper"),
+            (2, "traffic.light"),
+            (3, "a/b"),
+            (10, "42"),
+            (11, "it's"),
+        ])
+    );
+}
+
+#[test]
+fn parses_json_and_lists() {
+    let names = parse_names(r#"{"0": "person", "1": "café", "2": "say \"hi\""}"#).unwrap();
+    assert_eq!(names, map(&[(0, "person"), (1, "café"), (2, "say \"hi\"")]));
+    let names = parse_names("['person', \"dog\",]").unwrap();
+    assert_eq!(names, map(&[(0, "person"), (1, "dog")]));
+    assert_eq!(parse_names("{}").unwrap(), BTreeMap::new());
+}
+
+#[test]
+fn rejects_malformed_names() {
+    for bad in [
+        "",
+        "person, dog",
+        "{0: 'person'",
+        "{0: 'person}",
+        "{zero: 'person'}",
+        "{0: 'person', 0: 'dog'}",
+        "{0: 'person' 1: 'dog'}",
+        "['person'] extra",
+    ] {
+        assert!(parse_names(bad).is_err(), "{:?}", bad);
+    }
+}
+
+#[test]
+fn reads_names_files() {
+    let dir = std::env::temp_dir();
+    let lines = dir.join("webcam-segmentation-names.txt");
+    std::fs::write(&lines, "person\nbicycle\n\n").unwrap();
+    assert_eq!(
+        read_names(lines.to_str().unwrap()).unwrap(),
+        map(&[(0, "person"), (1, "bicycle")])
+    );
+    let dict = dir.join("webcam-segmentation-names.json");
+    std::fs::write(&dict, "{\"5\": \"person\"}\n").unwrap();
+    assert_eq!(
+        read_names(dict.to_str().unwrap()).unwrap(),
+        map(&[(5, "person")])
+    );
+}
@@ -0,0 +1,119 @@
+#![allow(clippy::type_complexity)]
+
+use webcam_segmentation::{
+    non_max_suppression, non_max_suppression_with, Bbox, NmsConfig, Point2, SoftNms,
+};
+
+fn boxes(
+    xs: &[(f32, f32, f32, f32, usize, f32)],
+) -> Vec<(Bbox, Option<Vec<Point2>>, Option<Vec<f32>>)> {
+    xs.iter()
+        .map(|&(x, y, w, h, id, conf)| (Bbox::new(x, y, w, h, id, conf), None, None))
+        .collect()
+}
+
+fn confidences(xs: &[(Bbox, Option<Vec<Point2>>, Option<Vec<f32>>)]) -> Vec<f32> {
+    xs.iter().map(|x| x.0.confidence).collect()
+}
+
+#[test]
+fn suppresses_overlapping_boxes() {
+    let mut xs = boxes(&[
+        (0., 0., 100., 100., 0, 0.8),
+        (5., 5., 100., 100., 0, 0.9),
+        (300., 300., 50., 50., 0, 0.7),
+    ]);
+    non_max_suppression(&mut xs, 0.45);
+    assert_eq!(confidences(&xs), vec![0.9, 0.7]);
+}
+
+#[test]
+fn class_agnostic_by_default() {
+    let mut xs = boxes(&[(0., 0., 100., 100., 0, 0.9), (0., 0., 100., 100., 1, 0.8)]);
+    non_max_suppression(&mut xs, 0.45);
+    assert_eq!(confidences(&xs), vec![0.9]);
+}
+
+#[test]
+fn class_aware_keeps_other_classes() {
+    let mut xs = boxes(&[
+        (0., 0., 100., 100., 0, 0.9),
+        (0., 0., 100., 100., 1, 0.8),
+        (2., 2., 100., 100., 1, 0.7),
+    ]);
+    let config = NmsConfig {
+        class_aware: true,
+        ..NmsConfig::new(0.45)
+    };
+    non_max_suppression_with(&mut xs, &config);
+    assert_eq!(confidences(&xs), vec![0.9, 0.8]);
+    assert_eq!(xs[1].0.id, 1);
+}
+
+#[test]
+fn max_det_and_top_k() {
+    let disjoint = boxes(&[
+        (0., 0., 10., 10., 0, 0.5),
+        (100., 0., 10., 10., 0, 0.9),
+        (200., 0., 10., 10., 0, 0.7),
+        (300., 0., 10., 10., 0, 0.6),
+    ]);
+
+    let mut xs = disjoint.clone();
+    let config = NmsConfig {
+        max_det: Some(2),
+        ..NmsConfig::new(0.45)
+    };
+    non_max_suppression_with(&mut xs, &config);
+    assert_eq!(confidences(&xs), vec![0.9, 0.7]);
+
+    let mut xs = disjoint;
+    let config = NmsConfig {
+        top_k: Some(3),
+        ..NmsConfig::new(0.45)
+    };
+    non_max_suppression_with(&mut xs, &config);
+    assert_eq!(confidences(&xs), vec![0.9, 0.7, 0.6]);
+}
+
+#[test]
+fn nan_confidence_does_not_panic() {
+    let mut xs = boxes(&[
+        (0., 0., 10., 10., 0, f32::NAN),
+        (100., 0., 10., 10., 0, 0.9),
+    ]);
+    non_max_suppression(&mut xs, 0.45);
+    assert_eq!(confidences(&xs), vec![0.9]);
+}
+
+#[test]
+fn soft_nms_linear_decays_instead_of_dropping() {
+    let mut xs = boxes(&[(0., 0., 100., 100., 0, 0.9), (10., 0., 100., 100., 0, 0.8)]);
+    let iou = xs[0].0.iou(&xs[1].0);
+    let config = NmsConfig {
+        soft: Some(SoftNms::Linear),
+        ..NmsConfig::new(0.45)
+    };
+    non_max_suppression_with(&mut xs, &config);
+    assert_eq!(xs.len(), 2);
+    assert_eq!(xs[0].0.confidence, 0.9);
+    assert!((xs[1].0.confidence - 0.8 * (1.0 - iou)).abs() < 1e-6);
+}
+
+#[test]
+fn soft_nms_gaussian_drops_below_score_threshold() {
+    let mut xs = boxes(&[
+        (0., 0., 100., 100., 0, 0.9),
+        (0., 0., 100., 100., 0, 0.8),
+        (500., 0., 100., 100., 0, 0.3),
+    ]);
+    let config = NmsConfig {
+        soft: Some(SoftNms::Gaussian),
+        sigma: 0.5,
+        score_threshold: 0.25,
+        ..NmsConfig::new(0.45)
+    };
+    non_max_suppression_with(&mut xs, &config);
+    // identical box decays to 0.8 * exp(-2) ~= 0.108 and is dropped
+    assert_eq!(confidences(&xs), vec![0.9, 0.3]);
+}
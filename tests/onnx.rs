@@ -0,0 +1,69 @@
+use webcam_segmentation::{DType, ModelInfo};
+
+mod common;
+use common::field;
+
+fn dim(value: Option<u8>) -> Vec<u8> {
+    match value {
+        Some(v) => field(1, &[1 << 3, v]),
+        None => field(1, &field(2, b"batch")),
+    }
+}
+
+fn input(name: &str, elem_type: u8, dims: &[Option<u8>]) -> Vec<u8> {
+    let shape: Vec<u8> = dims.iter().flat_map(|&d| dim(d)).collect();
+    let mut tensor = vec![1 << 3, elem_type];
+    tensor.extend(field(2, &shape));
+    let mut x = field(1, name.as_bytes());
+    x.extend(field(2, &field(1, &tensor)));
+    field(11, &x)
+}
+
+#[test]
+fn reads_inputs_and_metadata() {
+    let mut graph = input("images", 10, &[None, Some(3), Some(64), Some(96)]);
+    graph.extend(input("weight", 1, &[Some(3)]));
+    graph.extend(field(5, &field(8, b"weight")));
+    let mut model = field(7, &graph);
+    let mut prop = field(1, b"layout");
+    prop.extend(field(2, b"nchw"));
+    model.extend(field(14, &prop));
+
+    let info = ModelInfo::parse(&model).unwrap();
+    // initializers are not inputs
+    assert_eq!(info.inputs.len(), 1);
+    let (name, dtype, dims) = &info.inputs[0];
+    assert_eq!(name, "images");
+    assert_eq!(*dtype, Some(DType::Float16));
+    assert_eq!(dims, &vec![-1, 3, 64, 96]);
+    assert_eq!(info.metadata("layout").as_deref(), Some("nchw"));
+    assert_eq!(info.metadata("names"), None);
+
+    // truncated buffers are rejected instead of read past
+    assert!(ModelInfo::parse(&model[..model.len() - 3]).is_none());
+}
+
+#[cfg(feature = "ort")]
+#[test]
+fn missing_model_is_a_load_error() {
+    use webcam_segmentation::{Batch, Error, LogLevel, OptLevel, OrtBackend, OrtConfig, OrtEP};
+
+    let config = OrtConfig {
+        f: "does/not/exist.onnx".to_string(),
+        ep: OrtEP::Cpu,
+        trt_fp16: false,
+        batch: Batch::default(),
+        image_size: (None, None),
+        layout: None,
+        intra_threads: None,
+        inter_threads: None,
+        opt_level: OptLevel::All,
+        memory_arena: true,
+        log_level: LogLevel::Warning,
+        profiling: None,
+    };
+    match OrtBackend::build(config) {
+        Err(Error::Load { path, .. }) => assert_eq!(path, "does/not/exist.onnx"),
+        other => panic!("expected a load error, got {:?}", other.err()),
+    }
+}
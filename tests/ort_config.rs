@@ -0,0 +1,19 @@
+use clap::Parser;
+use webcam_segmentation::{Args, OrtConfig, OrtEP};
+
+fn ep(extra: &[&str]) -> OrtEP {
+    let base = ["test", "--model", "m.onnx", "--source", "/dev/null"];
+    let args = Args::parse_from(base.iter().chain(extra));
+    OrtConfig::from_args(&args).ep
+}
+
+#[test]
+fn picks_the_execution_provider() {
+    assert_eq!(ep(&[]), OrtEP::Cpu);
+    assert_eq!(ep(&["--onednn"]), OrtEP::OneDnn);
+    assert_eq!(ep(&["--openvino"]), OrtEP::OpenVino);
+    // GPU providers win, then OpenVINO over the other CPU ones
+    assert_eq!(ep(&["--cuda", "--onednn"]), OrtEP::Cuda(0));
+    assert_eq!(ep(&["--trt", "--cuda", "--device-id", "1"]), OrtEP::Trt(1));
+    assert_eq!(ep(&["--onednn", "--openvino"]), OrtEP::OpenVino);
+}
@@ -0,0 +1,67 @@
+use ndarray::{arr1, arr2};
+use webcam_segmentation::OutputLayout;
+
+#[test]
+fn detects_layouts_from_shapes() {
+    // yolov8n-seg / yolo11n-seg, coco
+    assert_eq!(
+        OutputLayout::detect(&[1, 116, 8400], 32, Some(80), None),
+        OutputLayout::V8
+    );
+    // yolov5n-seg, coco
+    assert_eq!(
+        OutputLayout::detect(&[1, 25200, 117], 32, Some(80), None),
+        OutputLayout::V5
+    );
+    assert_eq!(
+        OutputLayout::detect(&[1, 25200, 117], 32, None, None),
+        OutputLayout::V5
+    );
+    // end-to-end head after built-in NMS
+    assert_eq!(
+        OutputLayout::detect(&[1, 300, 38], 32, Some(80), None),
+        OutputLayout::E2e
+    );
+    assert_eq!(
+        OutputLayout::detect(&[1, 300, 6], 0, None, Some("Ultralytics YOLOv10n model")),
+        OutputLayout::E2e
+    );
+}
+
+#[test]
+fn num_classes_from_head_width() {
+    assert_eq!(OutputLayout::V8.nc(&[1, 116, 8400], 32), Some(80));
+    assert_eq!(OutputLayout::V5.nc(&[1, 25200, 117], 32), Some(80));
+    assert_eq!(OutputLayout::E2e.nc(&[1, 300, 38], 32), None);
+    assert_eq!(OutputLayout::V8.nc(&[1, -1, -1], 32), None);
+}
+
+#[test]
+fn decodes_rows() {
+    // v8: anchors last, 2 classes, 1 mask coef
+    let preds = arr2(&[
+        [10.0, 0.0],
+        [20.0, 0.0],
+        [4.0, 0.0],
+        [6.0, 0.0],
+        [0.1, 0.0],
+        [0.7, 0.0],
+        [0.5, 0.0],
+    ]);
+    let rows = OutputLayout::V8.anchors(preds.view());
+    assert_eq!(rows.dim(), (2, 7));
+    let (bbox, id, confidence, coefs) = OutputLayout::V8.decode(rows.row(0), 2, 1);
+    assert_eq!((bbox, id, confidence), ([10.0, 20.0, 4.0, 6.0], 1, 0.7));
+    assert_eq!(coefs.to_vec(), vec![0.5]);
+
+    // v5: objectness scales the class score
+    let pred = arr1(&[10.0, 20.0, 4.0, 6.0, 0.5, 0.2, 0.8, 0.5]);
+    let (_, id, confidence, _) = OutputLayout::V5.decode(pred.view(), 2, 1);
+    assert_eq!((id, confidence), (1, 0.4));
+
+    // e2e: xyxy, score, class
+    let pred = arr1(&[8.0, 17.0, 12.0, 23.0, 0.9, 3.0, 0.5]);
+    let (bbox, id, confidence, coefs) = OutputLayout::E2e.decode(pred.view(), 80, 1);
+    assert_eq!((bbox, id, confidence), ([10.0, 20.0, 4.0, 6.0], 3, 0.9));
+    assert_eq!(coefs.to_vec(), vec![0.5]);
+}
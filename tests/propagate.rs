@@ -0,0 +1,37 @@
+use image::Luma;
+use webcam_segmentation::{translate, Bbox, BboxMotion, Mask};
+
+#[test]
+fn bbox_motion_extrapolates_velocity() {
+    let mut motion = BboxMotion::default();
+    assert_eq!(motion.offset(5), (0.0, 0.0));
+
+    motion.update(0, &Bbox::new_from_xywh(0., 0., 10., 10.));
+    motion.update(4, &Bbox::new_from_xywh(8., -4., 10., 10.));
+    assert_eq!(motion.offset(4), (0.0, 0.0));
+    assert_eq!(motion.offset(6), (4.0, -2.0));
+
+    motion.reset();
+    assert_eq!(motion.offset(6), (0.0, 0.0));
+}
+
+#[test]
+fn translate_shifts_and_clears() {
+    let src = Mask::from_fn(4, 3, |x, y| Luma([(y * 4 + x) as f32]));
+    let mut dst = Mask::from_pixel(4, 3, Luma([9.0]));
+
+    translate(&src, (1.0, 1.0), &mut dst);
+    assert_eq!(
+        dst.as_raw(),
+        &vec![0., 0., 0., 0., 0., 0., 1., 2., 0., 4., 5., 6.]
+    );
+
+    translate(&src, (-2.4, 0.0), &mut dst);
+    assert_eq!(
+        dst.as_raw(),
+        &vec![2., 3., 0., 0., 6., 7., 0., 0., 10., 11., 0., 0.]
+    );
+
+    translate(&src, (5.0, 0.0), &mut dst);
+    assert!(dst.as_raw().iter().all(|&x| x == 0.0));
+}
@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+use clap::{CommandFactory, FromArgMatches};
+use webcam_segmentation::{Cli, Error, ModelRegistry, SegmenterKind, SubCommand};
+
+fn models_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("webcam-segmentation-{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("fast.toml"),
+        r#"
+path = "yolov8n-seg.onnx"
+width = 320
+height = 256
+dtype = "fp16"
+conf = 0.4
+iou = 0.5
+classes = ["person"]
+names = "coco.names"
+description = "laptop"
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("portrait.toml"),
+        "path = \"/abs/modnet.onnx\"\ntask = \"matting\"\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("README.md"), "not a manifest").unwrap();
+    dir
+}
+
+fn parse(argv: &[&str]) -> (Cli, clap::ArgMatches) {
+    let matches = Cli::command()
+        .try_get_matches_from(std::iter::once("test").chain(argv.iter().copied()))
+        .unwrap();
+    (Cli::from_arg_matches(&matches).unwrap(), matches)
+}
+
+#[test]
+fn cli_is_consistent() {
+    Cli::command().debug_assert();
+    // subcommands need neither `--model` nor `--source`
+    let (cli, _) = parse(&["list-models", "--models-dir", "somewhere"]);
+    assert!(matches!(cli.command, Some(SubCommand::ListModels)));
+    assert_eq!(cli.args.models_dir, "somewhere");
+    assert!(Cli::command()
+        .try_get_matches_from(["test", "--source", "x"])
+        .is_err());
+}
+
+#[test]
+fn reads_manifests() {
+    let dir = models_dir("registry-read");
+    let registry = ModelRegistry::open(dir.to_str().unwrap()).unwrap();
+    let names: Vec<_> = registry.iter().map(|x| x.name.as_str()).collect();
+    assert_eq!(names, ["fast", "portrait"]);
+
+    let fast = registry.get("fast").unwrap();
+    assert_eq!(fast.path, dir.join("yolov8n-seg.onnx"));
+    assert_eq!(fast.names, Some(dir.join("coco.names")));
+    assert_eq!(
+        registry.get("portrait").unwrap().path,
+        PathBuf::from("/abs/modnet.onnx")
+    );
+
+    match registry.get("accurate") {
+        Err(Error::Config(reason)) => assert!(reason.contains("fast, portrait"), "{}", reason),
+        _ => panic!("expected an unknown model error"),
+    }
+}
+
+#[test]
+fn rejects_bad_manifests() {
+    let dir = models_dir("registry-bad");
+    std::fs::write(
+        dir.join("typo.toml"),
+        "path = \"x.onnx\"\nconfidence = 0.5\n",
+    )
+    .unwrap();
+    assert!(ModelRegistry::open(dir.to_str().unwrap()).is_err());
+    std::fs::write(dir.join("typo.toml"), "path = \"x.onnx\"\ntask = \"sam\"\n").unwrap();
+    assert!(ModelRegistry::open(dir.to_str().unwrap()).is_err());
+}
+
+#[test]
+fn manifest_fills_in_flags_not_given() {
+    let dir = models_dir("registry-resolve");
+    let dir = dir.to_str().unwrap();
+    let (cli, matches) = parse(&[
+        "--model-name",
+        "fast",
+        "--models-dir",
+        dir,
+        "--source",
+        "/dev/video0",
+        "--conf",
+        "0.6",
+    ]);
+    let mut args = cli.args;
+    ModelRegistry::resolve(&mut args, &matches).unwrap();
+    assert!(args.model.ends_with("yolov8n-seg.onnx"));
+    assert_eq!((args.width, args.height), (Some(320), Some(256)));
+    assert!(args.fp16);
+    assert_eq!(args.iou, 0.5);
+    assert_eq!(args.classes, ["person"]);
+    // given on the command line
+    assert_eq!(args.conf, 0.6);
+
+    let (cli, matches) = parse(&[
+        "--model-name",
+        "portrait",
+        "--models-dir",
+        dir,
+        "--source",
+        "x",
+    ]);
+    let mut args = cli.args;
+    ModelRegistry::resolve(&mut args, &matches).unwrap();
+    assert_eq!(args.segmenter, SegmenterKind::Matting);
+    assert_eq!(args.model, "/abs/modnet.onnx");
+}
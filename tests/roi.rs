@@ -0,0 +1,74 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use image::{DynamicImage, GenericImageView, Luma, Rgb, RgbImage};
+use webcam_segmentation::{Mask, Matte, RoiSegmenter, Segmenter};
+
+struct Bright {
+    // alpha = 1 on bright pixels, records the size of every input
+    seen: Arc<Mutex<Vec<(u32, u32)>>>,
+}
+
+impl Segmenter for Bright {
+    fn segment(&mut self, img: &DynamicImage) -> Result<Option<Matte>> {
+        self.seen.lock().unwrap().push(img.dimensions());
+        let rgb = img.to_rgb8();
+        let alpha = Mask::from_fn(rgb.width(), rgb.height(), |x, y| {
+            Luma([if rgb.get_pixel(x, y).0[0] > 128 {
+                1.0
+            } else {
+                0.0
+            }])
+        });
+        let matte = Matte { alpha, bbox: None };
+        Ok(matte.bounds().map(|_| matte))
+    }
+
+    fn summary(&self) {}
+}
+
+fn frame(x: u32, y: u32) -> DynamicImage {
+    // 40x80 person on a 640x360 frame
+    DynamicImage::ImageRgb8(RgbImage::from_fn(640, 360, |px, py| {
+        let inside = (x..x + 40).contains(&px) && (y..y + 80).contains(&py);
+        Rgb(if inside { [255; 3] } else { [0; 3] })
+    }))
+}
+
+#[test]
+fn crops_around_the_previous_person() {
+    let seen = Arc::new(Mutex::new(vec![]));
+    let mut model = RoiSegmenter::new(Box::new(Bright { seen: seen.clone() }), 0.25);
+
+    // full frame until someone is found
+    let matte = model.segment(&frame(100, 100)).unwrap().unwrap();
+    assert_eq!(matte.bounds().unwrap().xmin, 100.0);
+
+    // 40x80 bbox padded by 10 and 20 on each side
+    assert_eq!(model.roi((640, 360)), Some((90, 80, 60, 120)));
+    let matte = model.segment(&frame(105, 95)).unwrap().unwrap();
+    assert_eq!(seen.lock().unwrap()[1], (60, 120));
+
+    // mapped back into frame coordinates
+    assert_eq!(matte.alpha.dimensions(), (640, 360));
+    assert_eq!(matte.alpha.get_pixel(105, 95).0[0], 1.0);
+    assert_eq!(matte.alpha.get_pixel(104, 95).0[0], 0.0);
+    let bbox = matte.bbox.unwrap();
+    assert_eq!(
+        (bbox.xmin, bbox.ymin, bbox.width, bbox.height),
+        (105.0, 95.0, 40.0, 80.0)
+    );
+}
+
+#[test]
+fn falls_back_to_the_full_frame_when_lost() {
+    let seen = Arc::new(Mutex::new(vec![]));
+    let mut model = RoiSegmenter::new(Box::new(Bright { seen: seen.clone() }), 0.25);
+    model.segment(&frame(100, 100)).unwrap();
+
+    // person jumped outside the crop
+    let matte = model.segment(&frame(500, 200)).unwrap().unwrap();
+    assert_eq!(seen.lock().unwrap()[1..], [(60, 120), (640, 360)]);
+    assert_eq!(matte.bounds().unwrap().xmin, 500.0);
+    assert_eq!(model.track().unwrap().xmin, 500.0);
+}
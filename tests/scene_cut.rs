@@ -0,0 +1,26 @@
+#![cfg(feature = "ort")]
+
+use image::{DynamicImage, Rgb, RgbImage};
+use webcam_segmentation::SceneCut;
+
+fn solid(v: u8) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 36, Rgb([v, v, v])))
+}
+
+#[test]
+fn first_frame_is_not_a_cut() {
+    let mut cut = SceneCut::new(0.2);
+    assert!(!cut.update(&solid(0)));
+}
+
+#[test]
+fn detects_large_luma_changes_only() {
+    let mut cut = SceneCut::new(0.2);
+    cut.update(&solid(100));
+    // small drift, e.g. auto exposure
+    assert!(!cut.update(&solid(120)));
+    // hard cut to a bright scene
+    assert!(cut.update(&solid(250)));
+    // compared against the latest frame, not the first
+    assert!(!cut.update(&solid(250)));
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "tract")]
+
+use clap::Parser;
+use ndarray::{Array, IxDyn};
+use webcam_segmentation::{build_backend, Args, TensorView};
+
+mod common;
+use common::field;
+
+fn value_info(n: u8, name: &str, elem_type: u8, dims: &[u8]) -> Vec<u8> {
+    let shape: Vec<u8> = dims.iter().flat_map(|&d| field(1, &[1 << 3, d])).collect();
+    let mut tensor = vec![1 << 3, elem_type];
+    tensor.extend(field(2, &shape));
+    let mut x = field(1, name.as_bytes());
+    x.extend(field(2, &field(1, &tensor)));
+    field(n, &x)
+}
+
+fn identity_model(elem_type: u8, metadata: &[(&str, &str)]) -> Vec<u8> {
+    // images [1, 3, 8, 8] -> Identity -> out
+    let mut node = field(1, b"images");
+    node.extend(field(2, b"out"));
+    node.extend(field(4, b"Identity"));
+    let mut graph = field(1, &node);
+    graph.extend(field(2, b"g"));
+    graph.extend(value_info(11, "images", elem_type, &[1, 3, 8, 8]));
+    graph.extend(value_info(12, "out", elem_type, &[1, 3, 8, 8]));
+
+    let mut model = vec![1 << 3, 8]; // ir_version
+    model.extend(field(8, &[2 << 3, 13])); // opset 13
+    model.extend(field(7, &graph));
+    for (key, value) in [("author", "tests")].iter().chain(metadata) {
+        let mut prop = field(1, key.as_bytes());
+        prop.extend(field(2, value.as_bytes()));
+        model.extend(field(14, &prop));
+    }
+    model
+}
+
+fn tract_args(path: &std::path::Path) -> Args {
+    Args::parse_from([
+        "test",
+        "--model",
+        path.to_str().unwrap(),
+        "--source",
+        "/dev/null",
+        "--backend",
+        "tract",
+    ])
+}
+
+#[test]
+fn runs_an_onnx_model_without_onnxruntime() {
+    let path = std::env::temp_dir().join("webcam-segmentation-identity.onnx");
+    std::fs::write(&path, identity_model(1, &[])).unwrap(); // float
+    let mut engine = build_backend(&tract_args(&path)).unwrap();
+    assert_eq!((engine.batch(), engine.height(), engine.width()), (1, 8, 8));
+    assert_eq!(engine.output_shapes(), vec![vec![1, 3, 8, 8]]);
+    assert_eq!(engine.author().as_deref(), Some("tests"));
+
+    let xs = Array::from_shape_fn(IxDyn(&[1, 3, 8, 8]), |i| i[1] as f32 + i[3] as f32 / 8.0);
+    engine.run(xs.view().into(), false).unwrap();
+    assert_eq!(engine.outputs()[0], xs);
+}
+
+#[test]
+fn dequantises_integer_outputs() {
+    // same metadata as the ORT backend reads: (q - zero_point) * scale
+    let path = std::env::temp_dir().join("webcam-segmentation-identity-u8.onnx");
+    let metadata = [("output_scale", "0.5"), ("output_zero_point", "10")];
+    std::fs::write(&path, identity_model(2, &metadata)).unwrap(); // uint8
+    let mut engine = build_backend(&tract_args(&path)).unwrap();
+
+    let xs = Array::from_shape_fn(IxDyn(&[1, 3, 8, 8]), |i| {
+        (i[1] * 64 + i[2] * 8 + i[3]) as u8
+    });
+    engine.run(TensorView::U8(xs.view()), false).unwrap();
+    assert_eq!(engine.outputs()[0], xs.mapv(|x| (x as f32 - 10.0) * 0.5));
+}